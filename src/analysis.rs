@@ -0,0 +1,185 @@
+use crate::chords::Chord;
+use crate::harmony::roman_numeral;
+use crate::keys::Key;
+use crate::notes::{Note, notes_add, notes_sub};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AugmentedSixth {
+    Italian,
+    French,
+    German,
+}
+
+impl AugmentedSixth {
+    fn of_intervals(intervals: &[u8]) -> Option<Self> {
+        match intervals {
+            [0, 4, 10] => Some(AugmentedSixth::Italian),
+            [0, 4, 6, 10] => Some(AugmentedSixth::French),
+            [0, 4, 7, 10] => Some(AugmentedSixth::German),
+            _ => None,
+        }
+    }
+
+    pub fn numeral(self) -> &'static str {
+        match self {
+            AugmentedSixth::Italian => "It+6",
+            AugmentedSixth::French => "Fr+6",
+            AugmentedSixth::German => "Ger+6",
+        }
+    }
+}
+
+// Targets are scale degrees of the key, 0 for the tonic
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChordFunction {
+    Diatonic,
+    SecondaryDominant { target: usize },
+    SecondaryLeadingTone { target: usize },
+    Borrowed,
+    Neapolitan,
+    AugmentedSixth(AugmentedSixth),
+    TritoneSubstitution { target: usize },
+    Chromatic,
+}
+
+impl ChordFunction {
+    pub fn name(self) -> &'static str {
+        match self {
+            ChordFunction::Diatonic => "diatonic",
+            ChordFunction::SecondaryDominant { .. } => "secondary dominant",
+            ChordFunction::SecondaryLeadingTone { .. } => "secondary leading-tone chord",
+            ChordFunction::Borrowed => "borrowed from the parallel key",
+            ChordFunction::Neapolitan => "Neapolitan",
+            ChordFunction::AugmentedSixth(_) => "augmented sixth",
+            ChordFunction::TritoneSubstitution { .. } => "tritone substitution",
+            ChordFunction::Chromatic => "chromatic",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChordAnalysis {
+    pub numeral: String,
+    pub function: ChordFunction,
+}
+
+pub fn analyze_progression(key: Key, progression: &[(Note, &Chord)]) -> Vec<ChordAnalysis> {
+    progression
+        .iter()
+        .enumerate()
+        .map(|(i, &(root, chord))| analyze_chord(key, root, chord.intervals(), i + 1 == progression.len()))
+        .collect()
+}
+
+fn analyze_chord(key: Key, root: Note, intervals: &[u8], is_last: bool) -> ChordAnalysis {
+    let analysis = |numeral: String, function| ChordAnalysis { numeral, function };
+    let has = |i| intervals.contains(&i);
+    let notes = intervals.iter().map(|&i| notes_add(root, i)).collect::<Vec<_>>();
+    let semitones = notes_sub(root, key.tonic);
+
+    if key.is_diatonic(&notes) {
+        return analysis(key.roman_numeral(root, intervals), ChordFunction::Diatonic);
+    }
+    if semitones == 1 && intervals == [0, 4, 7] {
+        return analysis("N".to_string(), ChordFunction::Neapolitan);
+    }
+    if let Some(sixth) = AugmentedSixth::of_intervals(intervals).filter(|_| semitones == 8) {
+        return analysis(sixth.numeral().to_string(), ChordFunction::AugmentedSixth(sixth));
+    }
+
+    // A major tonic closing a minor key piece is a Picardy third rather than V/iv
+    let picardy = is_last && semitones == 0;
+    let dominant = has(4) && !has(11) && (intervals == [0, 4, 7] || has(10));
+    if let Some(target) = secondary_target(key, notes_sub(root, 7)).filter(|_| dominant && !picardy) {
+        let numeral = format!("{}/{}", roman_numeral(4, intervals), key.triad_numeral(target));
+        return analysis(numeral, ChordFunction::SecondaryDominant { target });
+    }
+    let diminished = has(3) && has(6) && !has(4) && !has(7);
+    if let Some(target) = secondary_target(key, notes_add(root, 1)).filter(|_| diminished) {
+        let numeral = format!("{}/{}", roman_numeral(6, intervals), key.triad_numeral(target));
+        return analysis(numeral, ChordFunction::SecondaryLeadingTone { target });
+    }
+
+    if key.parallel().is_diatonic(&notes) {
+        return analysis(key.roman_numeral(root, intervals), ChordFunction::Borrowed);
+    }
+
+    let target = key.scale().iter().position(|&i| i == notes_sub(semitones, 1));
+    if let Some(target) = target.filter(|_| has(4) && has(10)) {
+        let numeral = match target {
+            0 => format!("sub{}", roman_numeral(4, intervals)),
+            _ => format!("sub{}/{}", roman_numeral(4, intervals), key.triad_numeral(target)),
+        };
+        return analysis(numeral, ChordFunction::TritoneSubstitution { target });
+    }
+
+    analysis(key.roman_numeral(root, intervals), ChordFunction::Chromatic)
+}
+
+// Degree of a chord root that can be tonicized: diatonic, not the tonic and not a diminished triad
+fn secondary_target(key: Key, root: Note) -> Option<usize> {
+    let degree = key.scale().iter().position(|&i| i == notes_sub(root, key.tonic))?;
+    (degree != 0 && !key.triad_numeral(degree).contains('°')).then_some(degree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chord_symbols::parse_chord_symbol;
+    use crate::notes::*;
+
+    fn analyze(key: Key, symbols: &[&str]) -> Vec<(String, ChordFunction)> {
+        let chords = symbols
+            .iter()
+            .map(|s| {
+                let symbol = parse_chord_symbol(s).unwrap();
+                let chord = Chord::new(s, "", symbol.intervals);
+                (symbol.root.note(), chord)
+            })
+            .collect::<Vec<_>>();
+        let progression = chords.iter().map(|(root, chord)| (*root, chord)).collect::<Vec<_>>();
+        analyze_progression(key, &progression)
+            .into_iter()
+            .map(|a| (a.numeral, a.function))
+            .collect()
+    }
+
+    fn numerals(key: Key, symbols: &[&str]) -> Vec<String> {
+        analyze(key, symbols).into_iter().map(|(numeral, _)| numeral).collect()
+    }
+
+    #[test]
+    fn secondary_and_borrowed_chords_in_major() {
+        assert_eq!(
+            numerals(
+                Key::major(C),
+                &["C", "A7", "Dm7", "D7", "G7", "F#dim7", "G", "Fm", "Bb", "C"]
+            ),
+            ["I", "V7/ii", "ii7", "V7/V", "V7", "vii°7/V", "V", "iv", "bVII", "I"]
+        );
+        assert_eq!(
+            analyze(Key::major(C), &["E7"])[0].1,
+            ChordFunction::SecondaryDominant { target: 5 }
+        );
+        assert_eq!(analyze(Key::major(C), &["Fm"])[0].1, ChordFunction::Borrowed);
+    }
+
+    #[test]
+    fn chromatic_predominants_and_substitutions() {
+        assert_eq!(
+            numerals(
+                Key::minor(A),
+                &["Am", "Bb", "E7", "Am", "F7", "E", "Dm7", "Db7", "C", "A"]
+            ),
+            ["i", "N", "V7", "i", "Ger+6", "V", "iv7", "subV7/III", "III", "I"]
+        );
+        assert_eq!(
+            numerals(Key::major(C), &["Ab7(no5)", "Ab7b5", "Db7", "Eb7"]),
+            ["It+6", "Fr+6", "subV7", "subV7/ii"]
+        );
+        assert_eq!(
+            analyze(Key::minor(A), &["A", "Dm"])[0].1,
+            ChordFunction::SecondaryDominant { target: 3 }
+        );
+    }
+}
@@ -1,16 +1,19 @@
+use crate::notes::Pitch;
+use crate::temperament::Intonation;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use rand::Rng;
 use std::collections::VecDeque;
 use std::f32::consts::PI;
 
-pub fn play_frequency_sine_wave(frequency: f32) -> cpal::Stream {
+pub fn play_sine_wave(pitch: Pitch, intonation: &Intonation, volume: f32) -> cpal::Stream {
+    let frequency = intonation.frequency(pitch);
     let host = cpal::default_host();
     let device = host.default_output_device().unwrap();
     let config = device.default_output_config().unwrap();
 
     let sample_rate = config.sample_rate().0 as f32;
     let mut sample_clock = 0f32;
-    let volume = 0.5;
+    let volume = 0.5 * volume;
 
     let stream = device
         .build_output_stream(
@@ -30,14 +33,15 @@ pub fn play_frequency_sine_wave(frequency: f32) -> cpal::Stream {
     stream
 }
 
-pub fn play_frequency_sine_plus_harmonics(frequency: f32) -> cpal::Stream {
+pub fn play_sine_plus_harmonics(pitch: Pitch, intonation: &Intonation, volume: f32) -> cpal::Stream {
+    let frequency = intonation.frequency(pitch);
     let host = cpal::default_host();
     let device = host.default_output_device().unwrap();
     let config = device.default_output_config().unwrap();
 
     let sample_rate = config.sample_rate().0 as f32;
     let mut sample_clock = 0f32;
-    let volume = 0.5;
+    let volume = 0.5 * volume;
 
     let stream = device
         .build_output_stream(
@@ -58,14 +62,15 @@ pub fn play_frequency_sine_plus_harmonics(frequency: f32) -> cpal::Stream {
     stream
 }
 
-pub fn play_frequency_sawtooth(frequency: f32) -> cpal::Stream {
+pub fn play_sawtooth(pitch: Pitch, intonation: &Intonation, volume: f32) -> cpal::Stream {
+    let frequency = intonation.frequency(pitch);
     let host = cpal::default_host();
     let device = host.default_output_device().unwrap();
     let config = device.default_output_config().unwrap();
 
     let sample_rate = config.sample_rate().0 as f32;
     let mut sample_clock = 0f32;
-    let volume = 0.5;
+    let volume = 0.5 * volume;
 
     let stream = device
         .build_output_stream(
@@ -86,7 +91,8 @@ pub fn play_frequency_sawtooth(frequency: f32) -> cpal::Stream {
     stream
 }
 
-pub fn playe_frequency_karplus_strong(frequency: f32) -> cpal::Stream {
+pub fn play_karplus_strong(pitch: Pitch, intonation: &Intonation, volume: f32) -> cpal::Stream {
+    let frequency = intonation.frequency(pitch);
     let host = cpal::default_host();
     let device = host.default_output_device().unwrap();
     let config = device.default_output_config().unwrap();
@@ -100,7 +106,7 @@ pub fn playe_frequency_karplus_strong(frequency: f32) -> cpal::Stream {
             &config.into(),
             move |data: &mut [f32], _| {
                 for sample in data.iter_mut() {
-                    *sample = plucked_string.next_sample();
+                    *sample = volume * plucked_string.next_sample();
                 }
             },
             |err| eprintln!("Stream error: {}", err),
@@ -120,9 +126,9 @@ pub struct PluckedString {
 impl PluckedString {
     pub fn new(frequency: f32, sample_rate: f32) -> Self {
         let n_samples = (sample_rate / frequency).round() as usize;
-        let mut rng = rand::thread_rng();
+        let mut rng = rand::rng();
 
-        let buffer = (0..n_samples).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let buffer = (0..n_samples).map(|_| rng.random_range(-1.0..1.0)).collect();
 
         Self {
             buffer,
@@ -0,0 +1,263 @@
+use crate::chord_symbols::parse_chord_symbol;
+use crate::chords::{Chord, ChordMatch, MatchOptions, builtin_chords, chord_matches, identify_voicing_in};
+use crate::notes::Note;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChordLibrary {
+    chords: Vec<Chord>,
+}
+
+impl Default for ChordLibrary {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ChordLibrary {
+    pub fn new(chords: Vec<Chord>) -> Self {
+        Self { chords }
+    }
+
+    pub fn builtin() -> Self {
+        Self::new(builtin_chords())
+    }
+
+    pub fn chords(&self) -> &[Chord] {
+        &self.chords
+    }
+
+    pub fn find(&self, intervals: &[u8]) -> Option<&Chord> {
+        self.chords.iter().find(|c| !c.is_alias() && c.matches(intervals))
+    }
+
+    pub fn position(&self, chord: &Chord) -> Option<usize> {
+        self.chords.iter().position(|c| std::ptr::eq(c, chord))
+    }
+
+    pub fn merge(&mut self, chords: Vec<Chord>) {
+        for chord in chords {
+            match self.chords.iter_mut().find(|c| c.name() == chord.name()) {
+                Some(existing) => *existing = chord,
+                None => self.chords.push(chord),
+            }
+        }
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> Result<(), ChordLibraryError> {
+        let source = std::fs::read_to_string(path).map_err(|error| ChordLibraryError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        self.load_toml(&source)
+    }
+
+    pub fn load_toml(&mut self, source: &str) -> Result<(), ChordLibraryError> {
+        let file = toml::from_str::<ChordFile>(source).map_err(|e| ChordLibraryError::Syntax {
+            line: e.span().map(|span| line_of(source, span.start)),
+            message: e.message().to_string(),
+        })?;
+
+        let mut library = if file.replace_builtin {
+            ChordLibrary::new(vec![])
+        } else {
+            self.clone()
+        };
+        let mut names = HashSet::new();
+        for definition in &file.chords {
+            let line = line_of(source, definition.span().start);
+            let definition = definition.get_ref();
+            let invalid = |message: String| ChordLibraryError::InvalidEntry {
+                name: definition.name.clone(),
+                line,
+                message,
+            };
+
+            if definition.name.trim().is_empty() {
+                return Err(invalid("name must not be empty".to_string()));
+            }
+            if !names.insert(definition.name.as_str()) {
+                return Err(invalid("chord is defined more than once".to_string()));
+            }
+            let intervals = definition.intervals().map_err(invalid)?;
+            let duplicate = library
+                .chords
+                .iter()
+                .find(|c| c.name() != definition.name && !c.is_alias() && c.matches(&intervals));
+            match (duplicate, definition.alias) {
+                (Some(duplicate), false) => {
+                    return Err(invalid(format!(
+                        "same intervals as '{}', mark it with `alias = true`",
+                        duplicate.name()
+                    )));
+                }
+                (None, true) => {
+                    return Err(invalid(
+                        "no other chord has the same intervals, remove `alias = true`".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+
+            let chord = if definition.alias {
+                Chord::alias(&definition.name, &definition.suffix, intervals)
+            } else {
+                Chord::new(&definition.name, &definition.suffix, intervals)
+            };
+            library.merge(vec![chord]);
+        }
+
+        if library.chords.is_empty() {
+            return Err(ChordLibraryError::Empty);
+        }
+        *self = library;
+        Ok(())
+    }
+
+    pub fn possible_chords(&self, notes: &[u8], options: &MatchOptions) -> Vec<ChordMatch<'_>> {
+        chord_matches(&self.chords, notes, None, options)
+    }
+
+    pub fn identify_voicing(&self, voicing: &[Note], options: &MatchOptions) -> Vec<ChordMatch<'_>> {
+        identify_voicing_in(&self.chords, voicing, options)
+    }
+}
+
+#[derive(Debug)]
+pub enum ChordLibraryError {
+    Io { path: PathBuf, error: std::io::Error },
+    Syntax { line: Option<usize>, message: String },
+    InvalidEntry { name: String, line: usize, message: String },
+    Empty,
+}
+
+impl fmt::Display for ChordLibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChordLibraryError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            ChordLibraryError::Syntax {
+                line: Some(line),
+                message,
+            } => write!(f, "line {line}: {message}"),
+            ChordLibraryError::Syntax { line: None, message } => write!(f, "{message}"),
+            ChordLibraryError::InvalidEntry { name, line, message } => {
+                write!(f, "line {line}: chord '{name}': {message}")
+            }
+            ChordLibraryError::Empty => write!(f, "the library has no chords"),
+        }
+    }
+}
+
+impl std::error::Error for ChordLibraryError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChordFile {
+    #[serde(default)]
+    replace_builtin: bool,
+    #[serde(default, rename = "chord")]
+    chords: Vec<toml::Spanned<ChordDefinition>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChordDefinition {
+    name: String,
+    #[serde(default)]
+    suffix: String,
+    intervals: Option<Vec<u8>>,
+    symbol: Option<String>,
+    #[serde(default)]
+    alias: bool,
+}
+
+impl ChordDefinition {
+    fn intervals(&self) -> Result<Vec<u8>, String> {
+        match (&self.intervals, &self.symbol) {
+            (Some(intervals), None) => {
+                if intervals.first() != Some(&0) {
+                    return Err("intervals must start with the root (0)".to_string());
+                }
+                if let Some(i) = intervals.iter().find(|&&i| i > 11) {
+                    return Err(format!("interval {i} is out of range 0..=11"));
+                }
+                if !intervals.windows(2).all(|w| w[0] < w[1]) {
+                    return Err("intervals must be ascending without duplicates".to_string());
+                }
+                Ok(intervals.clone())
+            }
+            (None, Some(symbol)) => parse_chord_symbol(&format!("C{symbol}"))
+                .map(|symbol| symbol.intervals)
+                .map_err(|e| format!("invalid symbol '{symbol}': {e}")),
+            _ => Err("exactly one of `intervals` or `symbol` is required".to_string()),
+        }
+    }
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_and_overrides_builtin_chords() {
+        let mut library = ChordLibrary::builtin();
+        let builtin_count = library.chords().len();
+        library
+            .load_toml(
+                r#"
+[[chord]]
+name = "Major Triad"
+suffix = "maj"
+intervals = [0, 4, 7]
+
+[[chord]]
+name = "Kenny Barron Minor"
+suffix = "m11(kb)"
+symbol = "m11"
+alias = true
+"#,
+            )
+            .unwrap();
+        assert_eq!(library.chords().len(), builtin_count + 1);
+        assert_eq!(library.find(&[0, 4, 7]).unwrap().short_name("C"), "Cmaj");
+    }
+
+    #[test]
+    fn reports_the_offending_entry_and_line() {
+        let error = ChordLibrary::builtin()
+            .load_toml(
+                "[[chord]]\nname = \"Good\"\nintervals = [0, 1, 2]\n\n[[chord]]\nname = \"Bad\"\nintervals = [0, 12]\n",
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 5: chord 'Bad': interval 12 is out of range 0..=11"
+        );
+    }
+
+    #[test]
+    fn rejects_aliases_of_nothing() {
+        let error = ChordLibrary::builtin()
+            .load_toml("[[chord]]\nname = \"Lonely\"\nintervals = [0, 1, 6]\nalias = true\n")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "line 1: chord 'Lonely': no other chord has the same intervals, remove `alias = true`"
+        );
+    }
+
+    #[test]
+    fn rejects_replacing_the_builtin_chords_with_nothing() {
+        let mut library = ChordLibrary::builtin();
+        let error = library.load_toml("replace_builtin = true\n").unwrap_err();
+        assert!(matches!(error, ChordLibraryError::Empty));
+        assert_eq!(library, ChordLibrary::builtin());
+    }
+}
@@ -0,0 +1,414 @@
+use crate::chord_library::ChordLibrary;
+use crate::chords::Chord;
+use crate::notes::SpelledNote;
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChordSymbol {
+    pub root: SpelledNote,
+    pub intervals: Vec<u8>,
+    pub bass: Option<SpelledNote>,
+}
+
+impl ChordSymbol {
+    pub fn chord<'a>(&self, library: &'a ChordLibrary) -> Option<&'a Chord> {
+        library.find(&self.intervals)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChordSymbolError {
+    Empty,
+    InvalidRoot(String),
+    InvalidBass(String),
+    UnexpectedInput { position: usize, rest: String },
+    UnsupportedDegree { position: usize, degree: u8 },
+}
+
+impl fmt::Display for ChordSymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChordSymbolError::Empty => write!(f, "empty chord symbol"),
+            ChordSymbolError::InvalidRoot(s) => write!(f, "'{s}' does not start with a note name (A-G)"),
+            ChordSymbolError::InvalidBass(s) => write!(f, "invalid bass note '{s}' after '/'"),
+            ChordSymbolError::UnexpectedInput { position, rest } => {
+                write!(f, "unexpected '{rest}' at position {position}")
+            }
+            ChordSymbolError::UnsupportedDegree { position, degree } => {
+                write!(f, "unsupported degree {degree} at position {position}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChordSymbolError {}
+
+impl std::str::FromStr for ChordSymbol {
+    type Err = ChordSymbolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_chord_symbol(s)
+    }
+}
+
+const MAJOR_ALIASES: [&str; 8] = ["maj", "Maj", "MAJ", "ma", "Ma", "M", "Δ", "∆"];
+const MINOR_ALIASES: [&str; 5] = ["min", "Min", "mi", "m", "-"];
+const DIMINISHED_ALIASES: [&str; 3] = ["dim", "°", "o"];
+const HALF_DIMINISHED_ALIASES: [&str; 2] = ["ø", "Ø"];
+const AUGMENTED_ALIASES: [&str; 2] = ["aug", "+"];
+
+struct ChordBuilder {
+    third: Option<u8>,
+    fifth: Option<u8>,
+    seventh: Option<u8>,
+    major_seventh: bool,
+    diminished: bool,
+    extensions: BTreeSet<u8>,
+    omitted: BTreeSet<u8>,
+}
+
+impl ChordBuilder {
+    fn seventh(&self) -> u8 {
+        if self.major_seventh {
+            11
+        } else if self.diminished {
+            9
+        } else {
+            10
+        }
+    }
+
+    fn intervals(&self) -> Vec<u8> {
+        let mut intervals = BTreeSet::from([0]);
+        intervals.extend(self.third);
+        intervals.extend(self.fifth);
+        intervals.extend(self.seventh);
+        intervals.extend(&self.extensions);
+        intervals.retain(|i| !self.omitted.contains(i));
+        intervals.into_iter().collect()
+    }
+}
+
+pub fn parse_chord_symbol(symbol: &str) -> Result<ChordSymbol, ChordSymbolError> {
+    let symbol = symbol.trim();
+    if symbol.is_empty() {
+        return Err(ChordSymbolError::Empty);
+    }
+
+    let (root, rest) =
+        SpelledNote::parse_prefix(symbol).ok_or_else(|| ChordSymbolError::InvalidRoot(symbol.to_string()))?;
+
+    let (body, bass) = match rest.rfind('/') {
+        Some(i) if !rest[i + 1..].starts_with(|c: char| c.is_ascii_digit()) => {
+            let bass = rest[i + 1..].trim();
+            let bass = bass
+                .parse()
+                .map_err(|_| ChordSymbolError::InvalidBass(bass.to_string()))?;
+            (&rest[..i], Some(bass))
+        }
+        _ => (rest, None),
+    };
+
+    let offset = symbol.len() - rest.len();
+    let intervals = parse_chord_body(body, offset)?;
+    Ok(ChordSymbol { root, intervals, bass })
+}
+
+fn parse_chord_body(body: &str, offset: usize) -> Result<Vec<u8>, ChordSymbolError> {
+    let mut chord = ChordBuilder {
+        third: Some(4),
+        fifth: Some(7),
+        seventh: None,
+        major_seventh: false,
+        diminished: false,
+        extensions: BTreeSet::new(),
+        omitted: BTreeSet::new(),
+    };
+    let mut rest = body;
+    let position = |rest: &str| offset + body.len() - rest.len();
+
+    // Quality
+    if let Some(r) = strip_any(rest, &HALF_DIMINISHED_ALIASES) {
+        chord.third = Some(3);
+        chord.fifth = Some(6);
+        chord.seventh = Some(10);
+        rest = r.strip_prefix('7').unwrap_or(r);
+    } else if let Some(r) = strip_any(rest, &DIMINISHED_ALIASES).filter(|r| !r.starts_with("mit")) {
+        chord.third = Some(3);
+        chord.fifth = Some(6);
+        chord.diminished = true;
+        rest = r;
+    } else if let Some(r) = strip_any(rest, &AUGMENTED_ALIASES) {
+        chord.fifth = Some(8);
+        rest = r;
+    } else if let Some(r) = strip_any(rest, &MAJOR_ALIASES).filter(|r| !r.starts_with("in") && !r.starts_with("dd")) {
+        chord.major_seventh = true;
+        rest = r;
+        if (rest.is_empty() || rest.starts_with('(')) && (body.starts_with('Δ') || body.starts_with('∆')) {
+            chord.seventh = Some(11);
+        }
+    } else if let Some(r) = strip_any(rest, &MINOR_ALIASES) {
+        chord.third = Some(3);
+        rest = r;
+        if let Some(r) = strip_any(rest, &MAJOR_ALIASES) {
+            chord.major_seventh = true;
+            rest = r;
+        }
+    }
+
+    // Main number
+    if let Some(r) = strip_any(rest, &["6/9", "69"]) {
+        chord.extensions.extend([2, 9]);
+        rest = r;
+    } else if let Some((number, r)) = parse_number(rest) {
+        match number {
+            5 if rest.len() == body.len() => {
+                chord.third = None;
+            }
+            // Shorthand for "add2" and "sus4"
+            2 if rest.len() == body.len() => {
+                chord.extensions.insert(2);
+            }
+            4 if rest.len() == body.len() => {
+                chord.third = None;
+                chord.extensions.insert(5);
+            }
+            6 => {
+                chord.extensions.insert(9);
+            }
+            7 | 9 | 11 | 13 => {
+                chord.seventh = Some(chord.seventh());
+                if number >= 9 {
+                    chord.extensions.insert(2);
+                }
+                if number >= 11 {
+                    chord.extensions.insert(5);
+                }
+                if number >= 13 {
+                    chord.extensions.insert(9);
+                }
+            }
+            _ => {
+                return Err(ChordSymbolError::UnsupportedDegree {
+                    position: position(rest),
+                    degree: number,
+                });
+            }
+        }
+        rest = r;
+    } else if chord.major_seventh && chord.seventh.is_none() && chord.third == Some(4) {
+        // A lone "maj" or "M" is just a major triad
+        chord.major_seventh = false;
+    }
+
+    // Modifiers
+    loop {
+        rest = rest.trim_start_matches([' ', '(', ')', ',']);
+        if rest.is_empty() {
+            break;
+        }
+        let start = position(rest);
+        let unsupported = |degree| ChordSymbolError::UnsupportedDegree {
+            position: start,
+            degree,
+        };
+
+        if let Some(r) = rest.strip_prefix("sus") {
+            let (number, r) = parse_number(r).unwrap_or((4, r));
+            chord.third = None;
+            chord.extensions.insert(match number {
+                2 => 2,
+                4 => 5,
+                _ => return Err(unsupported(number)),
+            });
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("add") {
+            let (alteration, r) = parse_accidental(r);
+            let (number, r) = parse_number(r).ok_or_else(|| unexpected(start, rest))?;
+            let interval = natural_interval(number).ok_or_else(|| unsupported(number))?;
+            chord
+                .extensions
+                .insert((interval as i8 + alteration).rem_euclid(12) as u8);
+            rest = r;
+        } else if let Some(r) = strip_any(rest, &["omit", "no"]) {
+            let (number, r) = parse_number(r.trim_start()).ok_or_else(|| unexpected(start, rest))?;
+            let omitted: &[u8] = match number {
+                1 => &[0],
+                3 => &[3, 4],
+                5 => &[6, 7, 8],
+                _ => return Err(unsupported(number)),
+            };
+            chord.omitted.extend(omitted);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("alt") {
+            chord.seventh = Some(10);
+            chord.fifth = None;
+            chord.extensions.extend([1, 3, 6, 8]);
+            rest = r;
+        } else if let Some(r) = strip_any(rest, &["maj7", "Maj7", "M7", "Δ7", "∆7", "Δ", "∆"]) {
+            chord.seventh = Some(11);
+            rest = r;
+        } else if let Some(r) = strip_any(rest, &AUGMENTED_ALIASES).filter(|r| parse_number(r).is_none()) {
+            chord.fifth = Some(8);
+            rest = r;
+        } else {
+            let (alteration, r) = parse_accidental(rest);
+            let (number, r) = match parse_number(r) {
+                Some(parsed) if alteration != 0 => parsed,
+                _ => return Err(unexpected(start, rest)),
+            };
+            let interval = natural_interval(number).ok_or_else(|| unsupported(number))?;
+            let altered = (interval as i8 + alteration).rem_euclid(12) as u8;
+            match number {
+                5 => chord.fifth = Some(altered),
+                9 => {
+                    chord.extensions.remove(&2);
+                    chord.extensions.insert(altered);
+                    chord.seventh.get_or_insert(10);
+                }
+                11 => {
+                    chord.extensions.remove(&5);
+                    chord.extensions.insert(altered);
+                    chord.seventh.get_or_insert(10);
+                }
+                13 => {
+                    chord.extensions.remove(&9);
+                    chord.extensions.insert(altered);
+                    chord.seventh.get_or_insert(10);
+                }
+                _ => {
+                    chord.extensions.insert(altered);
+                }
+            }
+            rest = r;
+        }
+    }
+
+    Ok(chord.intervals())
+}
+
+fn unexpected(position: usize, rest: &str) -> ChordSymbolError {
+    ChordSymbolError::UnexpectedInput {
+        position,
+        rest: rest.to_string(),
+    }
+}
+
+fn strip_any<'a>(s: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    prefixes.iter().find_map(|p| s.strip_prefix(p))
+}
+
+fn parse_number(s: &str) -> Option<(u8, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok().map(|n| (n, &s[end..]))
+}
+
+fn parse_accidental(s: &str) -> (i8, &str) {
+    match s.chars().next() {
+        Some(c @ ('b' | '♭' | '-')) => (-1, &s[c.len_utf8()..]),
+        Some(c @ ('#' | '♯' | '+')) => (1, &s[c.len_utf8()..]),
+        _ => (0, s),
+    }
+}
+
+fn natural_interval(degree: u8) -> Option<u8> {
+    match degree {
+        1 => Some(0),
+        2 | 9 => Some(2),
+        3 => Some(4),
+        4 | 11 => Some(5),
+        5 => Some(7),
+        6 | 13 => Some(9),
+        7 => Some(11),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intervals(symbol: &str) -> Vec<u8> {
+        parse_chord_symbol(symbol).unwrap().intervals
+    }
+
+    #[test]
+    fn reads_quality_aliases() {
+        for symbol in ["Cmaj7", "CM7", "CΔ7", "CΔ", "Cma7"] {
+            assert_eq!(intervals(symbol), [0, 4, 7, 11], "{symbol}");
+        }
+        for symbol in ["Cm", "Cmin", "Cmi", "C-"] {
+            assert_eq!(intervals(symbol), [0, 3, 7], "{symbol}");
+        }
+        for symbol in ["Cdim7", "C°7", "Co7"] {
+            assert_eq!(intervals(symbol), [0, 3, 6, 9], "{symbol}");
+        }
+        for symbol in ["Cø", "Cø7", "Cm7b5"] {
+            assert_eq!(intervals(symbol), [0, 3, 6, 10], "{symbol}");
+        }
+        assert_eq!(intervals("CM"), [0, 4, 7]);
+        assert_eq!(intervals("Caug"), [0, 4, 8]);
+        assert_eq!(intervals("CmM7"), [0, 3, 7, 11]);
+    }
+
+    #[test]
+    fn reads_numbers_and_modifiers() {
+        assert_eq!(intervals("C2"), [0, 2, 4, 7]);
+        assert_eq!(intervals("C4"), [0, 5, 7]);
+        assert_eq!(intervals("C5"), [0, 7]);
+        assert_eq!(intervals("C6/9"), [0, 2, 4, 7, 9]);
+        assert_eq!(intervals("C7sus4"), [0, 5, 7, 10]);
+        assert_eq!(intervals("C7#9"), [0, 3, 4, 7, 10]);
+        assert_eq!(intervals("Cadd9"), [0, 2, 4, 7]);
+        assert_eq!(intervals("C7(no3)"), [0, 7, 10]);
+        assert_eq!(intervals("Comit3"), [0, 7]);
+        assert_eq!(intervals("Comit5"), [0, 4]);
+        assert_eq!(intervals("Cm7 omit5"), [0, 3, 10]);
+    }
+
+    #[test]
+    fn reads_slash_basses() {
+        let symbol = parse_chord_symbol("Am7/G").unwrap();
+        assert_eq!(symbol.root, "A".parse().unwrap());
+        assert_eq!(symbol.intervals, [0, 3, 7, 10]);
+        assert_eq!(symbol.bass, Some("G".parse().unwrap()));
+        assert_eq!(parse_chord_symbol("C6/9").unwrap().bass, None);
+        assert_eq!(parse_chord_symbol("D/F#").unwrap().bass, Some("F#".parse().unwrap()));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(parse_chord_symbol("  "), Err(ChordSymbolError::Empty));
+        assert_eq!(
+            parse_chord_symbol("Hm"),
+            Err(ChordSymbolError::InvalidRoot("Hm".to_string()))
+        );
+        assert_eq!(
+            parse_chord_symbol("C/Q"),
+            Err(ChordSymbolError::InvalidBass("Q".to_string()))
+        );
+        assert_eq!(
+            parse_chord_symbol("Cxyz"),
+            Err(ChordSymbolError::UnexpectedInput {
+                position: 1,
+                rest: "xyz".to_string()
+            })
+        );
+        assert_eq!(
+            parse_chord_symbol("C8"),
+            Err(ChordSymbolError::UnsupportedDegree { position: 1, degree: 8 })
+        );
+        assert_eq!(
+            parse_chord_symbol("Csus3"),
+            Err(ChordSymbolError::UnsupportedDegree { position: 1, degree: 3 })
+        );
+        let sharps = format!("C{}", "#".repeat(130));
+        assert_eq!(
+            parse_chord_symbol(&sharps),
+            Err(ChordSymbolError::InvalidRoot(sharps.clone()))
+        );
+        assert!(parse_chord_symbol("C##").is_ok());
+        assert!(parse_chord_symbol("Gbbb").is_err());
+    }
+}
@@ -1,10 +1,13 @@
-use crate::notes::note_name;
+use crate::notes::{Note, SpelledNote, notes_add, notes_sub, root_spelling, spell_in_key};
+use serde::{Deserialize, Serialize};
 use std::sync::LazyLock;
 
+#[derive(Clone, Debug, PartialEq)]
 pub struct Chord {
     name: String,
     suffix: String,
     intervals: Vec<u8>,
+    alias: bool,
 }
 
 impl Chord {
@@ -13,9 +16,25 @@ impl Chord {
             name: name.to_string(),
             suffix: suffix.to_string(),
             intervals,
+            alias: false,
         }
     }
 
+    pub fn alias(name: &str, suffix: &str, intervals: Vec<u8>) -> Self {
+        Self {
+            alias: true,
+            ..Self::new(name, suffix, intervals)
+        }
+    }
+
+    pub fn is_alias(&self) -> bool {
+        self.alias
+    }
+
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
     pub fn matches(&self, intervals: &[u8]) -> bool {
         intervals == self.intervals
     }
@@ -24,51 +43,412 @@ impl Chord {
         &self.name
     }
 
+    pub fn intervals(&self) -> &[u8] {
+        &self.intervals
+    }
+
+    pub fn spell(&self, root: SpelledNote) -> Vec<SpelledNote> {
+        self.intervals
+            .iter()
+            .map(|&i| root.spell_interval(i, interval_degree(&self.intervals, i)))
+            .collect()
+    }
+
     pub fn short_name(&self, note: &str) -> String {
         note.to_string() + &self.suffix
     }
 }
 
-pub fn all_chords() -> &'static Vec<Chord> {
-    static ALL_CHORDS: LazyLock<Vec<Chord>> = LazyLock::new(|| {
-        vec![
-            // Triads
-            Chord::new("Power Chord", "5", vec![0, 7]),
-            Chord::new("Major Triad", "", vec![0, 4, 7]),
-            Chord::new("Minor Triad", "m", vec![0, 3, 7]),
-            Chord::new("Diminished Triad", "dim", vec![0, 3, 6]),
-            // 7 chords
-            Chord::new("Major 7", "M7", vec![0, 4, 7, 11]),
-            Chord::new("Dominant 7", "7", vec![0, 4, 7, 10]),
-            Chord::new("Minor 7", "m7", vec![0, 3, 7, 10]),
-            Chord::new("Minor 7 Flat 5", "m7(b5)", vec![0, 3, 6, 10]),
-            Chord::new("Diminished 7", "dim7", vec![0, 3, 6, 9]),
-            // other chords
-            Chord::new("Major 6", "6", vec![0, 4, 7, 9]),
-            Chord::new("Major 6/9", "6/9", vec![0, 2, 4, 7, 9]),
-            Chord::new("Major 9", "M9", vec![0, 2, 4, 7, 11]),
-            Chord::new("Major 11", "M11", vec![0, 2, 4, 5, 7, 11]),
-            Chord::new("Major 13", "M13", vec![0, 2, 4, 5, 7, 9, 11]),
-            // TODO add more chords
-        ]
-    });
-    &*ALL_CHORDS
-}
-
-pub fn possible_chords(notes: &[u8]) -> Vec<String> {
+fn interval_degree(intervals: &[u8], interval: u8) -> u8 {
+    let has = |i| intervals.contains(&i);
+    match interval {
+        0 => 1,
+        1 | 2 => 2,
+        3 if has(4) => 2,
+        3 | 4 => 3,
+        5 => 4,
+        6 if has(7) => 4,
+        6 | 7 => 5,
+        8 if has(7) => 6,
+        8 => 5,
+        9 if has(3) && has(6) && !has(10) && !has(11) => 7,
+        9 => 6,
+        _ => 7,
+    }
+}
+
+pub fn all_chords() -> &'static [Chord] {
+    static ALL_CHORDS: LazyLock<Vec<Chord>> = LazyLock::new(builtin_chords);
+    &ALL_CHORDS
+}
+
+pub fn builtin_chords() -> Vec<Chord> {
+    vec![
+        // Triads
+        Chord::new("Power Chord", "5", vec![0, 7]),
+        Chord::new("Major Triad", "", vec![0, 4, 7]),
+        Chord::new("Minor Triad", "m", vec![0, 3, 7]),
+        Chord::new("Diminished Triad", "dim", vec![0, 3, 6]),
+        Chord::new("Augmented Triad", "aug", vec![0, 4, 8]),
+        Chord::new("Suspended 2", "sus2", vec![0, 2, 7]),
+        Chord::new("Suspended 4", "sus4", vec![0, 5, 7]),
+        Chord::new("Major Flat 5", "(b5)", vec![0, 4, 6]),
+        // 7 chords
+        Chord::new("Major 7", "M7", vec![0, 4, 7, 11]),
+        Chord::new("Dominant 7", "7", vec![0, 4, 7, 10]),
+        Chord::new("Minor 7", "m7", vec![0, 3, 7, 10]),
+        Chord::new("Minor 7 Flat 5", "m7(b5)", vec![0, 3, 6, 10]),
+        Chord::alias("Half-Diminished 7", "ø7", vec![0, 3, 6, 10]),
+        Chord::new("Diminished 7", "dim7", vec![0, 3, 6, 9]),
+        Chord::new("Minor Major 7", "mM7", vec![0, 3, 7, 11]),
+        Chord::new("Diminished Major 7", "dim(M7)", vec![0, 3, 6, 11]),
+        Chord::new("Augmented 7", "aug7", vec![0, 4, 8, 10]),
+        Chord::alias("Dominant 7 Sharp 5", "7(#5)", vec![0, 4, 8, 10]),
+        Chord::new("Augmented Major 7", "augM7", vec![0, 4, 8, 11]),
+        Chord::alias("Major 7 Sharp 5", "M7(#5)", vec![0, 4, 8, 11]),
+        Chord::new("Dominant 7 Flat 5", "7(b5)", vec![0, 4, 6, 10]),
+        Chord::new("Dominant 7 Sus 4", "7sus4", vec![0, 5, 7, 10]),
+        Chord::new("Dominant 7 Sus 2", "7sus2", vec![0, 2, 7, 10]),
+        Chord::new("Major 7 Sus 4", "M7sus4", vec![0, 5, 7, 11]),
+        // 6 chords
+        Chord::new("Major 6", "6", vec![0, 4, 7, 9]),
+        Chord::new("Minor 6", "m6", vec![0, 3, 7, 9]),
+        Chord::new("Major 6/9", "6/9", vec![0, 2, 4, 7, 9]),
+        Chord::new("Minor 6/9", "m6/9", vec![0, 2, 3, 7, 9]),
+        // added tone chords
+        Chord::new("Add 9", "add9", vec![0, 2, 4, 7]),
+        Chord::alias("Add 2", "add2", vec![0, 2, 4, 7]),
+        Chord::new("Minor Add 9", "m(add9)", vec![0, 2, 3, 7]),
+        Chord::new("Add 11", "add11", vec![0, 4, 5, 7]),
+        Chord::new("Minor Add 11", "m(add11)", vec![0, 3, 5, 7]),
+        // 9 chords
+        Chord::new("Major 9", "M9", vec![0, 2, 4, 7, 11]),
+        Chord::new("Dominant 9", "9", vec![0, 2, 4, 7, 10]),
+        Chord::new("Minor 9", "m9", vec![0, 2, 3, 7, 10]),
+        Chord::new("Minor Major 9", "mM9", vec![0, 2, 3, 7, 11]),
+        Chord::new("Minor 9 Flat 5", "m9(b5)", vec![0, 2, 3, 6, 10]),
+        Chord::new("Dominant 9 Sus 4", "9sus4", vec![0, 2, 5, 7, 10]),
+        Chord::new("Dominant 9 Flat 5", "9(b5)", vec![0, 2, 4, 6, 10]),
+        Chord::new("Dominant 9 Sharp 5", "9(#5)", vec![0, 2, 4, 8, 10]),
+        Chord::new("Dominant 7 Flat 9", "7(b9)", vec![0, 1, 4, 7, 10]),
+        Chord::new("Dominant 7 Sharp 9", "7(#9)", vec![0, 3, 4, 7, 10]),
+        Chord::new("Minor 7 Flat 9", "m7(b9)", vec![0, 1, 3, 7, 10]),
+        Chord::new("Dominant 7 Sus 4 Flat 9", "7sus4(b9)", vec![0, 1, 5, 7, 10]),
+        // 11 chords
+        Chord::new("Major 11", "M11", vec![0, 2, 4, 5, 7, 11]),
+        Chord::new("Dominant 11", "11", vec![0, 2, 4, 5, 7, 10]),
+        Chord::new("Minor 11", "m11", vec![0, 2, 3, 5, 7, 10]),
+        Chord::new("Dominant 7 Sharp 11", "7(#11)", vec![0, 4, 6, 7, 10]),
+        Chord::new("Major 7 Sharp 11", "M7(#11)", vec![0, 4, 6, 7, 11]),
+        Chord::new("Dominant 9 Sharp 11", "9(#11)", vec![0, 2, 4, 6, 7, 10]),
+        // 13 chords
+        Chord::new("Major 13", "M13", vec![0, 2, 4, 5, 7, 9, 11]),
+        Chord::new("Dominant 13", "13", vec![0, 2, 4, 5, 7, 9, 10]),
+        Chord::new("Minor 13", "m13", vec![0, 2, 3, 5, 7, 9, 10]),
+        Chord::new("Dominant 13 Sharp 11", "13(#11)", vec![0, 2, 4, 6, 7, 9, 10]),
+        Chord::new("Dominant 7 Add 13", "7(add13)", vec![0, 4, 7, 9, 10]),
+        Chord::new("Dominant 7 Flat 13", "7(b13)", vec![0, 4, 7, 8, 10]),
+        Chord::new("Altered Dominant", "7alt", vec![0, 1, 3, 4, 6, 8, 10]),
+        // shells without the 5th
+        Chord::new("Dominant 7 Shell", "7(no5)", vec![0, 4, 10]),
+        Chord::new("Major 7 Shell", "M7(no5)", vec![0, 4, 11]),
+        Chord::new("Minor 7 Shell", "m7(no5)", vec![0, 3, 10]),
+        Chord::new("Dominant 9 Shell", "9(no5)", vec![0, 2, 4, 10]),
+        Chord::new("Major 9 Shell", "M9(no5)", vec![0, 2, 4, 11]),
+        Chord::new("Minor 9 Shell", "m9(no5)", vec![0, 2, 3, 10]),
+        // quartal voicings
+        Chord::new("Quartal Triad", "7sus4(no5)", vec![0, 5, 10]),
+        Chord::new("Quartal Tetrad", "m7(add11,no5)", vec![0, 3, 5, 10]),
+    ]
+}
+
+const TENSION_NAMES: [&str; 12] = ["root", "b9", "9", "#9", "3", "11", "#11", "5", "b13", "13", "b7", "7"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchOptions {
+    pub allow_omitted_root: bool,
+    pub allow_omitted_fifth: bool,
+    pub allow_omitted_ninth: bool,
+    pub allow_omitted_eleventh: bool,
+    pub max_extra_tones: usize,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            allow_omitted_root: false,
+            allow_omitted_fifth: true,
+            allow_omitted_ninth: true,
+            allow_omitted_eleventh: true,
+            max_extra_tones: 1,
+        }
+    }
+}
+
+impl MatchOptions {
+    pub fn exact() -> Self {
+        Self {
+            allow_omitted_root: false,
+            allow_omitted_fifth: false,
+            allow_omitted_ninth: false,
+            allow_omitted_eleventh: false,
+            max_extra_tones: 0,
+        }
+    }
+
+    fn omission_penalty(&self, chord: &Chord, interval: u8) -> Option<f32> {
+        let is_extended = chord.intervals.iter().any(|&i| i == 10 || i == 11) && chord.intervals.len() > 5;
+        match interval {
+            0 if self.allow_omitted_root => Some(0.3),
+            7 if self.allow_omitted_fifth => Some(0.1),
+            // Only in 13 chords, where the 9th is as disposable as the 11th
+            2 if self.allow_omitted_ninth && is_extended && chord.intervals.contains(&9) => Some(0.1),
+            5 if self.allow_omitted_eleventh && is_extended => Some(0.1),
+            _ => None,
+        }
+    }
+}
+
+const EXTRA_TONE_PENALTY: f32 = 0.35;
+const INVERSION_PENALTY: f32 = 0.05;
+const SLASH_BASS_PENALTY: f32 = 0.15;
+const INVERSION_NAMES: [&str; 3] = ["1st inversion", "2nd inversion", "3rd inversion"];
+
+#[derive(Clone, Debug)]
+pub struct ChordMatch<'a> {
+    pub root: Note,
+    pub spelled_root: SpelledNote,
+    pub chord: &'a Chord,
+    pub missing: Vec<u8>,
+    pub extra: Vec<u8>,
+    pub bass: Option<Note>,
+    pub score: f32,
+}
+
+impl ChordMatch<'_> {
+    // Spells the root the way the key with this tonic writes it: G#m rather than Abm in E major
+    pub fn in_key(mut self, tonic: SpelledNote) -> Self {
+        self.spelled_root = spell_in_key(self.root, tonic);
+        self
+    }
+
+    pub fn short_name(&self) -> String {
+        self.chord.short_name(&self.spelled_root.to_string())
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+
+    pub fn inversion(&self) -> Option<u8> {
+        let bass_interval = notes_sub(self.bass?, self.root);
+        if !self.chord.intervals.contains(&bass_interval) {
+            return None;
+        }
+        match interval_degree(&self.chord.intervals, bass_interval) {
+            3 => Some(1),
+            5 => Some(2),
+            7 => Some(3),
+            _ => None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        let mut description = self.short_name();
+        if let Some(bass) = self.bass {
+            description += &format!("/{}", self.spell_note(bass));
+        }
+        for &interval in &self.missing {
+            description += &format!(" (no {})", TENSION_NAMES[interval as usize]);
+        }
+        for &interval in &self.extra {
+            description += &format!(" (add {})", TENSION_NAMES[interval as usize]);
+        }
+        if let Some(inversion) = self.inversion() {
+            description += &format!(" ({})", INVERSION_NAMES[inversion as usize - 1]);
+        }
+        description
+    }
+
+    pub fn tones(&self) -> Vec<SpelledNote> {
+        self.chord.spell(self.spelled_root)
+    }
+
+    pub fn spell_note(&self, note: Note) -> SpelledNote {
+        self.chord
+            .intervals()
+            .iter()
+            .zip(self.tones())
+            .find(|&(&i, _)| notes_add(self.root, i) == note)
+            .map(|(_, tone)| tone)
+            .unwrap_or_else(|| spell_in_key(note, self.spelled_root))
+    }
+}
+
+pub fn possible_chords(notes: &[u8], options: &MatchOptions) -> Vec<ChordMatch<'static>> {
+    chord_matches(all_chords(), notes, None, options)
+}
+
+pub fn identify_voicing(voicing: &[Note], options: &MatchOptions) -> Vec<ChordMatch<'static>> {
+    identify_voicing_in(all_chords(), voicing, options)
+}
+
+pub(crate) fn identify_voicing_in<'a>(
+    chords: &'a [Chord],
+    voicing: &[Note],
+    options: &MatchOptions,
+) -> Vec<ChordMatch<'a>> {
+    let mut notes = voicing.to_vec();
+    notes.sort_unstable();
+    notes.dedup();
+    chord_matches(chords, &notes, voicing.first().copied(), options)
+}
+
+pub(crate) fn chord_matches<'a>(
+    chords: &'a [Chord],
+    notes: &[u8],
+    bass: Option<Note>,
+    options: &MatchOptions,
+) -> Vec<ChordMatch<'a>> {
     let mut result = Vec::new();
     if notes.is_empty() {
         return result;
     }
-    for &root in notes.iter() {
-        let mut intervals = notes.iter().map(|&n| (n + 12 - root) % 12).collect::<Vec<_>>();
+    for root in 0..12 {
+        if !notes.contains(&root) && !options.allow_omitted_root {
+            continue;
+        }
+        let mut intervals = notes.iter().map(|&n| notes_sub(n, root)).collect::<Vec<_>>();
         intervals.sort_unstable();
-        for chord in all_chords().iter() {
-            if chord.matches(&intervals) {
-                result.push(chord.short_name(note_name(root).as_str()) + ", " + chord.name());
+        for chord in chords.iter().filter(|c| !c.is_alias()) {
+            if let Some(chord_match) = match_chord(root, chord, &intervals, bass, options) {
+                result.push(chord_match);
             }
         }
     }
 
+    result.sort_by(|a, b| b.score.total_cmp(&a.score));
     result
 }
+
+fn match_chord<'a>(
+    root: Note,
+    chord: &'a Chord,
+    intervals: &[u8],
+    bass: Option<Note>,
+    options: &MatchOptions,
+) -> Option<ChordMatch<'a>> {
+    let mut score = 1.0;
+    let mut missing = Vec::new();
+    for &interval in chord.intervals.iter().filter(|i| !intervals.contains(i)) {
+        score -= options.omission_penalty(chord, interval)?;
+        missing.push(interval);
+    }
+    let bass = bass.filter(|&b| b != root);
+    let bass_interval = bass.map(|b| notes_sub(b, root));
+    let extra = intervals
+        .iter()
+        .copied()
+        .filter(|i| !chord.intervals.contains(i) && Some(*i) != bass_interval)
+        .collect::<Vec<_>>();
+    if extra.len() > options.max_extra_tones {
+        return None;
+    }
+    let present = chord.intervals.len() - missing.len();
+    if present < 2 || missing.len() >= present {
+        return None;
+    }
+    score -= extra.len() as f32 * EXTRA_TONE_PENALTY;
+    score -= match bass_interval {
+        Some(i) if chord.intervals.contains(&i) => INVERSION_PENALTY,
+        Some(_) => SLASH_BASS_PENALTY,
+        None => 0.0,
+    };
+
+    Some(ChordMatch {
+        root,
+        spelled_root: root_spelling(root),
+        chord,
+        missing,
+        extra,
+        bass,
+        score,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chord_symbols::parse_chord_symbol;
+    use crate::notes::*;
+
+    #[test]
+    fn interval_sets_are_unique_unless_marked_as_alias() {
+        let chords = all_chords().iter().filter(|c| !c.is_alias()).collect::<Vec<_>>();
+        for (i, a) in chords.iter().enumerate() {
+            for b in &chords[i + 1..] {
+                assert_ne!(a.intervals(), b.intervals(), "{} and {}", a.name(), b.name());
+            }
+        }
+        for alias in all_chords().iter().filter(|c| c.is_alias()) {
+            assert!(
+                chords.iter().any(|c| c.matches(alias.intervals())),
+                "{} is not an alias of any chord",
+                alias.name()
+            );
+        }
+    }
+
+    #[test]
+    fn intervals_are_sorted_and_start_at_root() {
+        for chord in all_chords() {
+            assert_eq!(chord.intervals()[0], 0, "{}", chord.name());
+            assert!(chord.intervals().windows(2).all(|w| w[0] < w[1]), "{}", chord.name());
+            assert!(chord.intervals().iter().all(|&i| i < 12), "{}", chord.name());
+        }
+    }
+
+    #[test]
+    fn finds_chords_with_omitted_fifth_ranked_below_exact_matches() {
+        let matches = possible_chords(&[C, D, E, AS], &MatchOptions::default());
+        assert_eq!(matches[0].description(), "C9(no5)");
+        assert!(matches.iter().any(|m| m.description() == "C9 (no 5)"));
+        assert!(
+            possible_chords(&[C, D, E, AS], &MatchOptions::exact())
+                .iter()
+                .all(|m| m.is_exact())
+        );
+    }
+
+    #[test]
+    fn reports_inversions_and_slash_basses() {
+        let matches = identify_voicing(&[E, C, G, C, E], &MatchOptions::default());
+        assert_eq!(matches[0].description(), "C/E (1st inversion)");
+
+        let matches = identify_voicing(&[C, D, FS, A], &MatchOptions::default());
+        assert!(matches.iter().any(|m| m.description() == "D/C"));
+
+        let matches = identify_voicing(&[A, C, E], &MatchOptions::default());
+        assert_eq!(matches[0].description(), "Am");
+    }
+
+    #[test]
+    fn spells_the_root_from_the_key() {
+        let g_sharp_minor = identify_voicing(&[GS, DS, GS, B], &MatchOptions::default()).remove(0);
+        assert_eq!(g_sharp_minor.description(), "Abm");
+        let e_major = "E".parse().unwrap();
+        let g_sharp_minor = g_sharp_minor.in_key(e_major);
+        assert_eq!(g_sharp_minor.description(), "G#m");
+        let tones = g_sharp_minor.tones().iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(tones, ["G#", "B", "D#"]);
+        assert_eq!(g_sharp_minor.spell_note(DS).to_string(), "D#");
+    }
+
+    #[test]
+    fn suffixes_parse_to_their_intervals() {
+        for chord in all_chords() {
+            let symbol = parse_chord_symbol(&chord.short_name("C")).unwrap();
+            assert_eq!(symbol.intervals, chord.intervals(), "{}", chord.name());
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use crate::fret_pattern::{FretPattern, StringState};
+use std::cmp::Reverse;
+use std::fmt;
+
+// Widest fret span a hand can cover, from the index finger to the pinky
+pub const MAX_STRETCH: u8 = 5;
+
+const BARRE_PENALTY: f32 = 1.0;
+const BARRE_STRING_PENALTY: f32 = 0.1;
+const THUMB_PENALTY: f32 = 1.5;
+const STRETCH_PENALTY: f32 = 1.0;
+const FINGER_PENALTY: f32 = 0.3;
+const MUTED_INNER_STRING_PENALTY: f32 = 1.5;
+const EXTRA_FINGER_PENALTY: f32 = 100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Finger {
+    Thumb,
+    Index,
+    Middle,
+    Ring,
+    Pinky,
+}
+
+impl Finger {
+    fn from_number(number: u8) -> Option<Self> {
+        match number {
+            0 => Some(Finger::Thumb),
+            1 => Some(Finger::Index),
+            2 => Some(Finger::Middle),
+            3 => Some(Finger::Ring),
+            4 => Some(Finger::Pinky),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Finger::Thumb => "T",
+            Finger::Index => "1",
+            Finger::Middle => "2",
+            Finger::Ring => "3",
+            Finger::Pinky => "4",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Barre {
+    pub finger: Finger,
+    pub fret: u8,
+    // String indices, first is the higher-pitched end of the barre
+    pub first_string: usize,
+    pub last_string: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FingeringProblem {
+    TooManyFingers { needed: usize },
+    TooWide { span: u8 },
+}
+
+impl fmt::Display for FingeringProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FingeringProblem::TooManyFingers { needed } => write!(f, "needs {needed} fingers"),
+            FingeringProblem::TooWide { span } => write!(f, "spans {span} frets"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fingering {
+    // One entry per string, None for open, muted or unassignable strings
+    pub fingers: Vec<Option<Finger>>,
+    pub barres: Vec<Barre>,
+    pub problem: Option<FingeringProblem>,
+    // 1.0 for open strings only, 0.0 for unplayable patterns
+    pub score: f32,
+}
+
+impl Fingering {
+    pub fn is_playable(&self) -> bool {
+        self.problem.is_none()
+    }
+
+    // Same order and notation as the fret pattern, with finger numbers in place of frets
+    pub fn label(&self, pattern: &FretPattern) -> String {
+        pattern
+            .strings()
+            .iter()
+            .zip(&self.fingers)
+            .rev()
+            .map(|(state, finger)| match (state, finger) {
+                (StringState::Muted, _) => "x",
+                (StringState::Open, _) => "0",
+                (StringState::Fretted(_), Some(finger)) => finger.label(),
+                (StringState::Fretted(_), None) => "?",
+            })
+            .collect()
+    }
+}
+
+pub fn fingering(pattern: &FretPattern) -> Fingering {
+    let strings = pattern.strings();
+    let mut notes = strings
+        .iter()
+        .enumerate()
+        .filter_map(|(string, state)| match *state {
+            StringState::Fretted(fret) => Some((string, fret)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // By fret, and from the bass up within a fret, so finger numbers never decrease
+    notes.sort_by_key(|&(string, fret)| (fret, Reverse(string)));
+
+    let mut best = None;
+    search(strings, &notes, &mut vec![], &mut best);
+    let (cost, numbers) = best.unwrap_or((0.0, vec![]));
+
+    let mut fingers = vec![None; strings.len()];
+    for (&(string, _), &number) in notes.iter().zip(&numbers) {
+        fingers[string] = Finger::from_number(number);
+    }
+    let barres = barres(&notes, &numbers);
+
+    let first = strings.iter().position(|s| !s.is_muted());
+    let last = strings.iter().rposition(|s| !s.is_muted());
+    let muted_inner_strings = match (first, last) {
+        (Some(first), Some(last)) => strings[first..=last].iter().filter(|s| s.is_muted()).count(),
+        _ => 0,
+    };
+    let cost = cost + MUTED_INNER_STRING_PENALTY * muted_inner_strings as f32;
+
+    let span = match (notes.first(), notes.last()) {
+        (Some(&(_, low)), Some(&(_, high))) => high - low + 1,
+        _ => 0,
+    };
+    let needed = numbers.iter().filter(|&&n| n > 0).max().copied().unwrap_or(0) as usize;
+    let problem = if needed > 4 {
+        Some(FingeringProblem::TooManyFingers { needed })
+    } else if span > MAX_STRETCH {
+        Some(FingeringProblem::TooWide { span })
+    } else {
+        None
+    };
+    let score = match problem {
+        Some(_) => 0.0,
+        None => 1.0 / (1.0 + 0.25 * cost),
+    };
+
+    Fingering {
+        fingers,
+        barres,
+        problem,
+        score,
+    }
+}
+
+// Finger numbers beyond 4 stand for the missing fingers of an unplayable pattern
+fn search(strings: &[StringState], notes: &[(usize, u8)], numbers: &mut Vec<u8>, best: &mut Option<(f32, Vec<u8>)>) {
+    if numbers.len() == notes.len() {
+        if let Some(cost) = assignment_cost(strings, notes, numbers)
+            && best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost)
+        {
+            *best = Some((cost, numbers.clone()));
+        }
+        return;
+    }
+
+    let (string, fret) = notes[numbers.len()];
+    let previous = numbers
+        .iter()
+        .zip(notes)
+        .rfind(|&(&n, _)| n > 0)
+        .map(|(&n, &(_, f))| (n, f));
+    let lowest = match previous {
+        Some((n, f)) if f == fret => n,
+        Some((n, _)) => n + 1,
+        None => 1,
+    };
+    // The thumb can only wrap around the neck to the lowest string
+    let thumb = (string + 1 == strings.len()).then_some(0);
+    for number in thumb.into_iter().chain(lowest..=notes.len() as u8) {
+        numbers.push(number);
+        search(strings, notes, numbers, best);
+        numbers.pop();
+    }
+}
+
+fn assignment_cost(strings: &[StringState], notes: &[(usize, u8)], numbers: &[u8]) -> Option<f32> {
+    let mut cost = 0.0;
+
+    let mut used = numbers.to_vec();
+    used.sort_unstable();
+    used.dedup();
+    for &number in &used {
+        let held = notes
+            .iter()
+            .zip(numbers)
+            .filter(|&(_, &n)| n == number)
+            .map(|(&(string, _), _)| string);
+        let (first, last) = held.fold((usize::MAX, 0), |(first, last), s| (first.min(s), last.max(s)));
+        if number == 0 {
+            cost += THUMB_PENALTY;
+        } else if first != last {
+            // Every string under a barre sounds the barre fret or a note fretted above it
+            let fret = notes[numbers.iter().position(|&n| n == number).unwrap()].1;
+            if strings[first..=last].iter().any(|s| s.fret().is_none_or(|f| f < fret)) {
+                return None;
+            }
+            cost += BARRE_PENALTY + BARRE_STRING_PENALTY * (last - first + 1) as f32;
+        }
+        cost += match number {
+            0..=4 => FINGER_PENALTY,
+            _ => EXTRA_FINGER_PENALTY,
+        };
+    }
+
+    // Fingers further apart than their neighbouring frets need a stretch
+    let placed = notes
+        .iter()
+        .zip(numbers)
+        .filter(|&(_, &n)| n > 0)
+        .map(|(&(_, fret), &n)| (n, fret))
+        .collect::<Vec<_>>();
+    for pair in placed.windows(2) {
+        let ((a, fret_a), (b, fret_b)) = (pair[0], pair[1]);
+        cost += STRETCH_PENALTY * (fret_b - fret_a).saturating_sub(b - a) as f32;
+    }
+    if let (Some(&(_, thumb_fret)), Some(&(_, lowest_fret))) = (
+        notes.iter().zip(numbers).find(|&(_, &n)| n == 0).map(|(note, _)| note),
+        placed.first(),
+    ) && thumb_fret > lowest_fret + 1
+    {
+        return None;
+    }
+
+    Some(cost)
+}
+
+fn barres(notes: &[(usize, u8)], numbers: &[u8]) -> Vec<Barre> {
+    let mut barres = vec![];
+    for number in 1..=4 {
+        let strings = notes
+            .iter()
+            .zip(numbers)
+            .filter(|&(_, &n)| n == number)
+            .map(|(&(string, fret), _)| (string, fret))
+            .collect::<Vec<_>>();
+        if strings.len() < 2 {
+            continue;
+        }
+        let first_string = strings.iter().map(|&(s, _)| s).min().unwrap();
+        let last_string = strings.iter().map(|&(s, _)| s).max().unwrap();
+        barres.push(Barre {
+            finger: Finger::from_number(number).unwrap(),
+            fret: strings[0].1,
+            first_string,
+            last_string,
+        });
+    }
+    barres
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(frets: &str) -> FretPattern {
+        frets.parse().unwrap()
+    }
+
+    #[test]
+    fn fingers_open_and_barre_chords() {
+        let c_major = pattern("x32010");
+        let fingering_c = fingering(&c_major);
+        assert_eq!(fingering_c.label(&c_major), "x32010");
+        assert!(fingering_c.is_playable() && fingering_c.barres.is_empty());
+
+        let f_major = pattern("133211");
+        let fingering_f = fingering(&f_major);
+        assert_eq!(fingering_f.label(&f_major), "134211");
+        assert_eq!(
+            fingering_f.barres,
+            [Barre {
+                finger: Finger::Index,
+                fret: 1,
+                first_string: 0,
+                last_string: 5
+            }]
+        );
+        assert!(fingering_f.score < fingering_c.score);
+    }
+
+    #[test]
+    fn flags_unplayable_patterns() {
+        let five_fingers = pattern("x12345");
+        assert_eq!(
+            fingering(&five_fingers).problem,
+            Some(FingeringProblem::TooManyFingers { needed: 5 })
+        );
+        assert_eq!(
+            fingering(&pattern("1xxxx8")).problem,
+            Some(FingeringProblem::TooWide { span: 8 })
+        );
+
+        // Muted strings in between rule out a barre
+        let muted_between = pattern("x1x1x1");
+        let fingering_muted = fingering(&muted_between);
+        assert_eq!(fingering_muted.label(&muted_between), "x1x2x3");
+        assert!(fingering_muted.barres.is_empty());
+    }
+}
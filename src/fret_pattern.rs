@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StringState {
+    Muted,
+    Open,
+    Fretted(u8),
+}
+
+impl StringState {
+    pub fn from_fret(fret: Option<u8>) -> Self {
+        match fret {
+            None => StringState::Muted,
+            Some(0) => StringState::Open,
+            Some(fret) => StringState::Fretted(fret),
+        }
+    }
+
+    // None for a muted string, 0 for an open one
+    pub fn fret(self) -> Option<u8> {
+        match self {
+            StringState::Muted => None,
+            StringState::Open => Some(0),
+            StringState::Fretted(fret) => Some(fret),
+        }
+    }
+
+    pub fn is_muted(self) -> bool {
+        self == StringState::Muted
+    }
+}
+
+// One state per string, in the guitar's string order: the highest string first
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "SavedFretPattern")]
+pub struct FretPattern {
+    strings: Vec<StringState>,
+}
+
+impl FretPattern {
+    pub fn new(strings: Vec<StringState>) -> Self {
+        Self { strings }
+    }
+
+    pub fn muted(strings: usize) -> Self {
+        Self::new(vec![StringState::Muted; strings])
+    }
+
+    pub fn from_frets(frets: &[Option<u8>]) -> Self {
+        Self::new(frets.iter().map(|&f| StringState::from_fret(f)).collect())
+    }
+
+    pub fn frets(&self) -> Vec<Option<u8>> {
+        self.strings.iter().map(|s| s.fret()).collect()
+    }
+
+    pub fn strings(&self) -> &[StringState] {
+        &self.strings
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn get(&self, string: usize) -> StringState {
+        self.strings.get(string).copied().unwrap_or(StringState::Muted)
+    }
+
+    pub fn fret(&self, string: usize) -> Option<u8> {
+        self.get(string).fret()
+    }
+
+    // Strings past the end are left alone, `get` reads them as muted
+    pub fn set(&mut self, string: usize, state: StringState) {
+        if let Some(current) = self.strings.get_mut(string) {
+            *current = state;
+        }
+    }
+
+    // Frets the string, or mutes it when that fret is already held
+    pub fn toggle(&mut self, string: usize, fret: u8) {
+        let state = StringState::from_fret(Some(fret));
+        match self.get(string) == state {
+            true => self.set(string, StringState::Muted),
+            false => self.set(string, state),
+        }
+    }
+
+    pub fn push(&mut self, state: StringState) {
+        self.strings.push(state);
+    }
+
+    pub fn remove(&mut self, string: usize) {
+        self.strings.remove(string);
+    }
+
+    pub fn is_all_muted(&self) -> bool {
+        self.strings.iter().all(|s| s.is_muted())
+    }
+
+    // Mutes the strings fretted above the last fret
+    pub fn clamp(&mut self, fret_count: u8) {
+        for state in &mut self.strings {
+            if state.fret().is_some_and(|f| f > fret_count) {
+                *state = StringState::Muted;
+            }
+        }
+    }
+}
+
+// Lowest string first, "x" for muted strings: "x32010". Frets past 9 need separators: "x-10-12-12-12-10"
+impl fmt::Display for FretPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = match self.strings.iter().any(|s| s.fret().is_some_and(|f| f > 9)) {
+            true => "-",
+            false => "",
+        };
+        let labels = self
+            .strings
+            .iter()
+            .rev()
+            .map(|s| s.fret().map_or("x".to_string(), |f| f.to_string()))
+            .collect::<Vec<_>>();
+        write!(f, "{}", labels.join(separator))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FretPatternError {
+    InvalidFret(String),
+}
+
+impl fmt::Display for FretPatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FretPatternError::InvalidFret(fret) => write!(f, "'{fret}' is neither a fret number nor 'x'"),
+        }
+    }
+}
+
+impl std::error::Error for FretPatternError {}
+
+// One character per string, or separated by "-", "," or spaces once frets go past 9
+impl FromStr for FretPattern {
+    type Err = FretPatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let is_separator = |c: char| c == '-' || c == ',' || c.is_whitespace();
+        let tokens = match s.contains(is_separator) {
+            true => s.split(is_separator).filter(|t| !t.is_empty()).collect::<Vec<_>>(),
+            false => s.char_indices().map(|(i, c)| &s[i..i + c.len_utf8()]).collect(),
+        };
+        let mut strings = tokens
+            .iter()
+            .map(|&token| match token {
+                "x" | "X" => Ok(StringState::Muted),
+                _ => token
+                    .parse::<u8>()
+                    .map(|fret| StringState::from_fret(Some(fret)))
+                    .map_err(|_| FretPatternError::InvalidFret(token.to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        strings.reverse();
+        Ok(Self::new(strings))
+    }
+}
+
+impl From<FretPattern> for String {
+    fn from(pattern: FretPattern) -> Self {
+        pattern.to_string()
+    }
+}
+
+// Saved app state before version 2 stored patterns as a list of frets, highest string first
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedFretPattern {
+    Notation(String),
+    Frets(Vec<Option<u8>>),
+}
+
+impl TryFrom<SavedFretPattern> for FretPattern {
+    type Error = FretPatternError;
+
+    fn try_from(saved: SavedFretPattern) -> Result<Self, Self::Error> {
+        match saved {
+            SavedFretPattern::Notation(notation) => notation.parse(),
+            SavedFretPattern::Frets(frets) => Ok(Self::from_frets(&frets)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_standard_notation() {
+        let c_major = "x32010".parse::<FretPattern>().unwrap();
+        assert_eq!(c_major.get(0), StringState::Open);
+        assert_eq!(c_major.get(4), StringState::Fretted(3));
+        assert_eq!(c_major.get(5), StringState::Muted);
+        assert_eq!(c_major.frets(), [Some(0), Some(1), Some(0), Some(2), Some(3), None]);
+        assert_eq!(c_major.to_string(), "x32010");
+
+        let high = "x-10-12-12-12-10".parse::<FretPattern>().unwrap();
+        assert_eq!(high.to_string(), "x-10-12-12-12-10");
+        assert_eq!("X 10 12 12 12 10".parse(), Ok(high));
+        assert_eq!("".parse(), Ok(FretPattern::default()));
+        assert_eq!(
+            "x3201o".parse::<FretPattern>(),
+            Err(FretPatternError::InvalidFret("o".to_string()))
+        );
+
+        let mut pattern = FretPattern::muted(3);
+        pattern.toggle(1, 0);
+        assert_eq!(pattern.to_string(), "x0x");
+        pattern.toggle(1, 0);
+        assert!(pattern.is_all_muted());
+
+        pattern.set(3, StringState::Open);
+        pattern.toggle(4, 2);
+        assert_eq!(pattern, FretPattern::muted(3));
+        assert_eq!(pattern.get(4), StringState::Muted);
+    }
+
+    #[test]
+    fn saves_as_notation_and_reads_old_fret_lists() {
+        #[derive(Serialize, Deserialize)]
+        struct Saved {
+            frets: FretPattern,
+        }
+
+        let pattern = FretPattern::from_frets(&[Some(0), Some(1), None]);
+        let source = toml::to_string(&Saved { frets: pattern.clone() }).unwrap();
+        assert_eq!(source.trim(), "frets = \"x10\"");
+        assert_eq!(toml::from_str::<Saved>(&source).unwrap().frets, pattern);
+
+        let old = SavedFretPattern::Frets(vec![Some(0), Some(1), None]);
+        assert_eq!(FretPattern::try_from(old), Ok(pattern));
+    }
+}
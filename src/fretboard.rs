@@ -0,0 +1,535 @@
+use crate::fingering::{Barre, Finger};
+use crate::fret_pattern::{FretPattern, StringState};
+use crate::guitar::Guitar;
+use crate::notes::note_name;
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Ui, pos2, vec2};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+const NECK_COLOR: Color32 = Color32::from_rgb(62, 42, 28);
+const FRET_COLOR: Color32 = Color32::from_rgb(185, 185, 190);
+const NUT_COLOR: Color32 = Color32::from_rgb(232, 226, 208);
+const STRING_COLOR: Color32 = Color32::from_rgb(205, 190, 160);
+const INLAY_COLOR: Color32 = Color32::from_rgb(215, 215, 205);
+const CAPO_COLOR: Color32 = Color32::from_rgb(35, 35, 38);
+const BARRE_COLOR: Color32 = Color32::from_rgb(120, 120, 125);
+const DOT_TEXT_COLOR: Color32 = Color32::WHITE;
+
+// The narrowest fret space, in string spacings, so dots never overlap high up the neck
+const MIN_FRET_GAP: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Orientation {
+    // Strings run left to right, highest string on top like tablature
+    #[default]
+    Horizontal,
+    // Strings run top to bottom, lowest string on the left like a chord chart
+    Vertical,
+}
+
+// Root, thirds, fifths and sevenths get their own colors, the remaining intervals count as tensions
+pub fn interval_color(interval: u8) -> Color32 {
+    match interval % 12 {
+        0 => Color32::from_rgb(210, 64, 54),
+        3 | 4 => Color32::from_rgb(58, 150, 78),
+        7 => Color32::from_rgb(52, 110, 200),
+        6 | 8 => Color32::from_rgb(100, 130, 200),
+        10 | 11 => Color32::from_rgb(150, 80, 190),
+        _ => Color32::from_rgb(200, 140, 40),
+    }
+}
+
+pub struct Dot {
+    pub string: usize,
+    pub fret: u8,
+    pub text: String,
+    pub color: Color32,
+    pub finger: Option<Finger>,
+}
+
+impl Dot {
+    pub fn new(string: usize, fret: u8, text: impl Into<String>, color: Color32) -> Self {
+        Self {
+            string,
+            fret,
+            text: text.into(),
+            color,
+            finger: None,
+        }
+    }
+
+    pub fn finger(mut self, finger: Option<Finger>) -> Self {
+        self.finger = finger;
+        self
+    }
+}
+
+pub struct FretboardResponse {
+    pub response: egui::Response,
+    // String and fret under the pointer, 0 for the open string
+    pub hovered: Option<(usize, u8)>,
+    pub clicked: Option<(usize, u8)>,
+}
+
+pub struct Fretboard<'a> {
+    guitar: &'a Guitar,
+    frets: RangeInclusive<u8>,
+    orientation: Orientation,
+    string_spacing: f32,
+    dots: Vec<Dot>,
+    voicing: Option<&'a FretPattern>,
+    barres: &'a [Barre],
+    hover_preview: bool,
+    fret_numbers: bool,
+    string_labels: bool,
+}
+
+impl<'a> Fretboard<'a> {
+    pub fn new(guitar: &'a Guitar) -> Self {
+        Self {
+            guitar,
+            frets: 1..=guitar.fret_count,
+            orientation: Orientation::default(),
+            string_spacing: 22.0,
+            dots: vec![],
+            voicing: None,
+            barres: &[],
+            hover_preview: false,
+            fret_numbers: true,
+            string_labels: false,
+        }
+    }
+
+    // The fret spaces to draw, the nut is only drawn when they start at the first fret
+    pub fn frets(mut self, frets: RangeInclusive<u8>) -> Self {
+        self.frets = frets;
+        self
+    }
+
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn string_spacing(mut self, string_spacing: f32) -> Self {
+        self.string_spacing = string_spacing;
+        self
+    }
+
+    pub fn dots(mut self, dots: Vec<Dot>) -> Self {
+        self.dots = dots;
+        self
+    }
+
+    // Marks muted strings with a cross and open strings with a ring beside the nut
+    pub fn voicing(mut self, pattern: &'a FretPattern) -> Self {
+        self.voicing = Some(pattern);
+        self
+    }
+
+    pub fn barres(mut self, barres: &'a [Barre]) -> Self {
+        self.barres = barres;
+        self
+    }
+
+    // Shows the note under the pointer as a faded dot
+    pub fn hover_preview(mut self, hover_preview: bool) -> Self {
+        self.hover_preview = hover_preview;
+        self
+    }
+
+    // Numbers every fret, otherwise only the first one when the nut is off screen
+    pub fn fret_numbers(mut self, fret_numbers: bool) -> Self {
+        self.fret_numbers = fret_numbers;
+        self
+    }
+
+    pub fn string_labels(mut self, string_labels: bool) -> Self {
+        self.string_labels = string_labels;
+        self
+    }
+
+    // Distance from the widget's edge to the string across the neck, to line other widgets up with the strings
+    pub fn string_offset(&self, string: usize) -> f32 {
+        Geometry::new(self).across(string as f32)
+    }
+
+    pub fn show(self, ui: &mut Ui) -> FretboardResponse {
+        if self.guitar.guitar_strings.is_empty() {
+            return FretboardResponse {
+                response: ui.allocate_response(egui::Vec2::ZERO, Sense::hover()),
+                hovered: None,
+                clicked: None,
+            };
+        }
+
+        let geometry = Geometry::new(&self);
+        let (rect, response) = ui.allocate_exact_size(geometry.size(), Sense::click());
+        let geometry = Geometry { rect, ..geometry };
+
+        let hovered = response.hover_pos().and_then(|p| geometry.position_at(p));
+        let clicked = response
+            .clicked()
+            .then(|| response.interact_pointer_pos())
+            .flatten()
+            .and_then(|p| geometry.position_at(p));
+
+        if ui.is_rect_visible(rect) {
+            self.paint(ui, &geometry, hovered);
+        }
+
+        FretboardResponse {
+            response,
+            hovered,
+            clicked,
+        }
+    }
+
+    fn paint(&self, ui: &Ui, geometry: &Geometry, hovered: Option<(usize, u8)>) {
+        let painter = ui.painter_at(geometry.rect);
+        let strings = self.guitar.guitar_strings.len() as f32;
+        let spacing = self.string_spacing;
+        let (first, last) = (geometry.first, geometry.last);
+
+        let neck = vec![
+            geometry.point(-0.5, geometry.fret_along(-0.5, first - 1)),
+            geometry.point(strings - 0.5, geometry.fret_along(strings - 0.5, first - 1)),
+            geometry.point(strings - 0.5, geometry.fret_along(strings - 0.5, last)),
+            geometry.point(-0.5, geometry.fret_along(-0.5, last)),
+        ];
+        painter.add(egui::Shape::convex_polygon(neck, NECK_COLOR, Stroke::NONE));
+
+        for fret in first..=last {
+            let along = |string: f32| geometry.fret_along(string, fret - 1) + geometry.fret_gap(string, fret) / 2.0;
+            let inlay =
+                |string: f32| painter.circle_filled(geometry.point(string, along(string)), spacing * 0.2, INLAY_COLOR);
+            match fret % 12 {
+                3 | 5 | 7 | 9 => {
+                    inlay((strings - 1.0) / 2.0);
+                }
+                0 => {
+                    inlay((strings - 1.0) * 0.25);
+                    inlay((strings - 1.0) * 0.75);
+                }
+                _ => {}
+            }
+        }
+
+        for fret in first - 1..=last {
+            let (width, color) = match fret {
+                0 => (spacing * 0.3, NUT_COLOR),
+                _ => (2.0, FRET_COLOR),
+            };
+            let ends = [-0.5, strings - 0.5].map(|s| geometry.point(s, geometry.fret_along(s, fret)));
+            painter.line_segment(ends, Stroke::new(width, color));
+        }
+
+        for (string, guitar_string) in self.guitar.guitar_strings.iter().enumerate() {
+            let s = string as f32;
+            let thickness = 1.0 + 2.0 * s / (strings - 1.0).max(1.0);
+            let ends = [geometry.open_along(), geometry.fret_along(s, last)].map(|along| geometry.point(s, along));
+            painter.line_segment(ends, Stroke::new(thickness, STRING_COLOR));
+
+            if self.string_labels {
+                painter.text(
+                    geometry.point(s, spacing * 0.9),
+                    Align2::CENTER_CENTER,
+                    guitar_string.tuning.to_string(),
+                    FontId::monospace(spacing * 0.55),
+                    ui.visuals().text_color(),
+                );
+            }
+
+            let capo = guitar_string.capo;
+            if capo >= first && capo <= last {
+                let along = geometry.fret_along(s, capo) - geometry.fret_gap(s, capo) * 0.2;
+                let ends = [s - 0.5, s + 0.5].map(|s| geometry.point(s, along));
+                painter.line_segment(ends, Stroke::new(spacing * 0.35, CAPO_COLOR));
+            }
+        }
+
+        let label_font = FontId::proportional(spacing * 0.5);
+        for fret in first..=last {
+            if !self.fret_numbers && (fret != first || first == 1) {
+                continue;
+            }
+            let along = geometry.fret_along(strings - 1.0, fret - 1) + geometry.fret_gap(strings - 1.0, fret) / 2.0;
+            painter.text(
+                geometry.label_point(along),
+                Align2::CENTER_CENTER,
+                fret.to_string(),
+                label_font.clone(),
+                ui.visuals().weak_text_color(),
+            );
+        }
+
+        let radius = spacing * 0.42;
+        if let Some(pattern) = self.voicing {
+            let stroke = Stroke::new(1.5, ui.visuals().text_color());
+            for (string, state) in pattern.strings().iter().enumerate() {
+                let center = geometry.point(string as f32, geometry.open_along());
+                match state {
+                    StringState::Muted => {
+                        let d = radius * 0.6;
+                        painter.line_segment([center + vec2(-d, -d), center + vec2(d, d)], stroke);
+                        painter.line_segment([center + vec2(-d, d), center + vec2(d, -d)], stroke);
+                    }
+                    StringState::Open => {
+                        painter.circle_stroke(center, radius * 0.7, stroke);
+                    }
+                    StringState::Fretted(_) => {}
+                }
+            }
+        }
+
+        for barre in self.barres {
+            let ends = [barre.first_string, barre.last_string].map(|s| geometry.dot_center(s, barre.fret));
+            painter.line_segment(ends, Stroke::new(radius * 1.6, BARRE_COLOR));
+            for end in ends {
+                painter.circle_filled(end, radius * 0.8, BARRE_COLOR);
+            }
+        }
+
+        let dot_font = FontId::proportional(radius * 1.1);
+        let finger_font = FontId::proportional(radius * 0.8);
+        for dot in &self.dots {
+            let center = geometry.dot_center(dot.string, dot.fret);
+            painter.circle_filled(center, radius, dot.color);
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                &dot.text,
+                dot_font.clone(),
+                DOT_TEXT_COLOR,
+            );
+            if let Some(finger) = dot.finger {
+                painter.text(
+                    center + vec2(radius, -radius),
+                    Align2::CENTER_CENTER,
+                    finger.label(),
+                    finger_font.clone(),
+                    ui.visuals().strong_text_color(),
+                );
+            }
+        }
+
+        if let Some((string, fret)) = hovered.filter(|_| self.hover_preview)
+            && !self.dots.iter().any(|d| d.string == string && d.fret == fret)
+        {
+            let center = geometry.dot_center(string, fret);
+            painter.circle_filled(center, radius, Color32::from_white_alpha(50));
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                note_name(self.guitar.fret_to_note(string, fret)),
+                dot_font,
+                DOT_TEXT_COLOR,
+            );
+        }
+    }
+}
+
+// Positions are worked out along the neck and across the strings, then turned to fit the orientation
+struct Geometry<'a> {
+    guitar: &'a Guitar,
+    rect: Rect,
+    orientation: Orientation,
+    spacing: f32,
+    first: u8,
+    last: u8,
+    // Room before the first fret line for string labels and open or muted markers
+    head: f32,
+    origin: f32,
+    pixels_per_mm: f32,
+}
+
+impl<'a> Geometry<'a> {
+    fn new(fretboard: &Fretboard<'a>) -> Self {
+        let guitar = fretboard.guitar;
+        let first = (*fretboard.frets.start()).max(1);
+        let last = (*fretboard.frets.end()).max(first);
+        let spacing = fretboard.string_spacing;
+        let head = spacing * if fretboard.string_labels { 2.8 } else { 1.4 };
+
+        let strings = 0..guitar.guitar_strings.len();
+        let origin = strings
+            .clone()
+            .map(|s| guitar.fret_position(s, first - 1))
+            .fold(f32::INFINITY, f32::min);
+        let narrowest = strings
+            .map(|s| guitar.fret_position(s, last) - guitar.fret_position(s, last - 1))
+            .fold(f32::INFINITY, f32::min);
+        Self {
+            guitar,
+            rect: Rect::NOTHING,
+            orientation: fretboard.orientation,
+            spacing,
+            first,
+            last,
+            head,
+            origin,
+            pixels_per_mm: MIN_FRET_GAP * spacing / narrowest.max(1.0),
+        }
+    }
+
+    fn size(&self) -> egui::Vec2 {
+        let strings = self.guitar.guitar_strings.len() as f32;
+        let along = (0..self.guitar.guitar_strings.len())
+            .map(|s| self.fret_along(s as f32, self.last))
+            .fold(0.0, f32::max)
+            + self.spacing * 0.5;
+        let across = strings * self.spacing + self.label_margin();
+        match self.orientation {
+            Orientation::Horizontal => vec2(along, across),
+            Orientation::Vertical => vec2(across, along),
+        }
+    }
+
+    fn label_margin(&self) -> f32 {
+        self.spacing * 1.2
+    }
+
+    // Fret lines are straight, so fanned frets are interpolated between the outer strings
+    fn fret_along(&self, string: f32, fret: u8) -> f32 {
+        let strings = self.guitar.guitar_strings.len();
+        let position = |s: usize| self.guitar.fret_position(s, fret);
+        let t = string / (strings - 1).max(1) as f32;
+        let mm = position(0) + (position(strings - 1) - position(0)) * t;
+        self.head + (mm - self.origin) * self.pixels_per_mm
+    }
+
+    fn fret_gap(&self, string: f32, fret: u8) -> f32 {
+        self.fret_along(string, fret) - self.fret_along(string, fret - 1)
+    }
+
+    fn open_along(&self) -> f32 {
+        self.head - self.spacing * 0.7
+    }
+
+    fn dot_center(&self, string: usize, fret: u8) -> Pos2 {
+        let s = string as f32;
+        let along = match fret {
+            0 => self.open_along(),
+            _ => self.fret_along(s, fret - 1) + self.fret_gap(s, fret) * 0.6,
+        };
+        self.point(s, along)
+    }
+
+    fn across(&self, string: f32) -> f32 {
+        let strings = self.guitar.guitar_strings.len() as f32;
+        match self.orientation {
+            Orientation::Horizontal => (string + 0.5) * self.spacing,
+            Orientation::Vertical => self.label_margin() + (strings - 0.5 - string) * self.spacing,
+        }
+    }
+
+    fn point(&self, string: f32, along: f32) -> Pos2 {
+        let across = self.across(string);
+        match self.orientation {
+            Orientation::Horizontal => pos2(self.rect.left() + along, self.rect.top() + across),
+            Orientation::Vertical => pos2(self.rect.left() + across, self.rect.top() + along),
+        }
+    }
+
+    // Fret numbers go below a horizontal neck and left of a vertical one
+    fn label_point(&self, along: f32) -> Pos2 {
+        let strings = self.guitar.guitar_strings.len() as f32;
+        match self.orientation {
+            Orientation::Horizontal => pos2(
+                self.rect.left() + along,
+                self.rect.top() + strings * self.spacing + self.label_margin() / 2.0,
+            ),
+            Orientation::Vertical => pos2(self.rect.left() + self.label_margin() / 2.0, self.rect.top() + along),
+        }
+    }
+
+    // Frets at or below a capo can't be played, so they are never hovered or clicked
+    fn position_at(&self, pointer: Pos2) -> Option<(usize, u8)> {
+        let (along, across) = match self.orientation {
+            Orientation::Horizontal => (pointer.x - self.rect.left(), pointer.y - self.rect.top()),
+            Orientation::Vertical => (pointer.y - self.rect.top(), pointer.x - self.rect.left()),
+        };
+        let strings = self.guitar.guitar_strings.len();
+        let string = (0..strings).find(|&s| (across - self.across(s as f32)).abs() <= self.spacing / 2.0)?;
+
+        let s = string as f32;
+        let fret = if along < self.fret_along(s, self.first - 1) {
+            0
+        } else {
+            (self.first..=self.last).find(|&fret| along <= self.fret_along(s, fret))?
+        };
+        let capo = self.guitar.guitar_strings[string].capo;
+        (fret == 0 || fret > capo).then_some((string, fret))
+    }
+}
+
+// Fret spaces for a chord chart of the voicing, from the nut when it fits in the first few frets
+pub fn diagram_frets(pattern: &FretPattern) -> RangeInclusive<u8> {
+    const DIAGRAM_FRETS: u8 = 4;
+    let fretted = pattern.strings().iter().filter_map(|s| match s {
+        StringState::Fretted(fret) => Some(*fret),
+        _ => None,
+    });
+    let low = fretted.clone().min().unwrap_or(1);
+    let high = fretted.max().unwrap_or(1);
+    match high <= DIAGRAM_FRETS {
+        true => 1..=DIAGRAM_FRETS,
+        false => low..=high.max(low + DIAGRAM_FRETS - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry<'a>(fretboard: &Fretboard<'a>) -> Geometry<'a> {
+        let geometry = Geometry::new(fretboard);
+        let rect = Rect::from_min_size(pos2(100.0, 50.0), geometry.size());
+        Geometry { rect, ..geometry }
+    }
+
+    #[test]
+    fn diagrams_start_at_the_nut_when_the_voicing_fits() {
+        let frets = |notation: &str| diagram_frets(&notation.parse().unwrap());
+        assert_eq!(frets("x32010"), 1..=4);
+        assert_eq!(frets("xx0000"), 1..=4);
+        assert_eq!(frets("x-x-x-x-x-x"), 1..=4);
+        assert_eq!(frets("x35553"), 3..=6);
+        assert_eq!(frets("8-10-10-9-8-8"), 8..=11);
+        assert_eq!(frets("x-5-x-x-x-12"), 5..=12);
+    }
+
+    #[test]
+    fn positions_round_trip_in_both_orientations() {
+        let mut guitar = Guitar::guitar_6_string_standard();
+        guitar.set_scale_lengths(648.0, 686.0);
+        guitar.perpendicular_fret = 7;
+        for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+            let fretboard = Fretboard::new(&guitar).orientation(orientation).frets(1..=12);
+            let geometry = geometry(&fretboard);
+            for string in 0..6 {
+                for fret in 0..=12 {
+                    let center = geometry.dot_center(string, fret);
+                    assert_eq!(geometry.position_at(center), Some((string, fret)), "{orientation:?}");
+                }
+            }
+            assert_eq!(
+                geometry.position_at(geometry.rect.right_bottom() + vec2(10.0, 10.0)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn frets_under_the_capo_cannot_be_picked() {
+        let mut guitar = Guitar::guitar_6_string_standard();
+        guitar.set_capo(2);
+        let fretboard = Fretboard::new(&guitar).frets(1..=5);
+        let geometry = geometry(&fretboard);
+        assert_eq!(geometry.position_at(geometry.dot_center(3, 0)), Some((3, 0)));
+        assert_eq!(geometry.position_at(geometry.dot_center(3, 1)), None);
+        assert_eq!(geometry.position_at(geometry.dot_center(3, 2)), None);
+        assert_eq!(geometry.position_at(geometry.dot_center(3, 3)), Some((3, 3)));
+        assert_eq!(fretboard.string_offset(0), geometry.across(0.0));
+    }
+}
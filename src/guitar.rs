@@ -1,62 +1,325 @@
+use crate::chords::Chord;
+use crate::fret_pattern::{FretPattern, StringState};
 use crate::notes::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-#[derive(PartialEq)]
+pub const DEFAULT_FRET_COUNT: u8 = 22;
+pub const MAX_FRET_SPAN: u8 = 4;
+// Scale lengths in millimetres: 25.5" for guitar, 34" for bass
+pub const GUITAR_SCALE_LENGTH: f32 = 648.0;
+pub const BASS_SCALE_LENGTH: f32 = 864.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct GuitarString {
-    pub tuning: Note,
+    pub tuning: Pitch,
+    // Fret the capo clamps this string at, 0 without a capo. Partial capos cover only some strings
+    pub capo: u8,
+    pub scale_length: f32,
 }
 
-#[derive(PartialEq)]
+impl GuitarString {
+    pub fn new(tuning: Pitch) -> Self {
+        Self {
+            tuning,
+            capo: 0,
+            scale_length: GUITAR_SCALE_LENGTH,
+        }
+    }
+
+    // Distance from the nut to the fret, for an equal-tempered fretboard
+    pub fn fret_distance(&self, fret: u8) -> f32 {
+        self.scale_length * (1.0 - 2f32.powf(-(fret as f32) / 12.0))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Guitar {
     pub guitar_strings: Vec<GuitarString>,
+    pub fret_count: u8,
+    // Fanned fret instruments line up all strings at this fret
+    pub perpendicular_fret: u8,
 }
 
 impl Guitar {
+    pub fn new(tunings: &[Pitch], fret_count: u8, scale_length: f32) -> Self {
+        let mut guitar = Self {
+            guitar_strings: tunings.iter().map(|&tuning| GuitarString::new(tuning)).collect(),
+            fret_count,
+            perpendicular_fret: 0,
+        };
+        guitar.set_scale_lengths(scale_length, scale_length);
+        guitar
+    }
+
     pub fn guitar_6_string_standard() -> Self {
-        Self {
-            guitar_strings: vec![
-                GuitarString { tuning: E },
-                GuitarString { tuning: B },
-                GuitarString { tuning: G },
-                GuitarString { tuning: D },
-                GuitarString { tuning: A },
-                GuitarString { tuning: E },
+        Self::new(
+            &[
+                Pitch::new(E, 4),
+                Pitch::new(B, 3),
+                Pitch::new(G, 3),
+                Pitch::new(D, 3),
+                Pitch::new(A, 2),
+                Pitch::new(E, 2),
             ],
-        }
+            DEFAULT_FRET_COUNT,
+            GUITAR_SCALE_LENGTH,
+        )
     }
 
     pub fn bass_4_string_standard() -> Self {
-        Self {
-            guitar_strings: vec![
-                GuitarString { tuning: G },
-                GuitarString { tuning: D },
-                GuitarString { tuning: A },
-                GuitarString { tuning: E },
-            ],
-        }
+        Self::new(
+            &[Pitch::new(G, 2), Pitch::new(D, 2), Pitch::new(A, 1), Pitch::new(E, 1)],
+            20,
+            BASS_SCALE_LENGTH,
+        )
     }
 
     pub fn bass_5_string_standard() -> Self {
-        Self {
-            guitar_strings: vec![
-                GuitarString { tuning: G },
-                GuitarString { tuning: D },
-                GuitarString { tuning: A },
-                GuitarString { tuning: E },
-                GuitarString { tuning: B },
+        Self::new(
+            &[
+                Pitch::new(G, 2),
+                Pitch::new(D, 2),
+                Pitch::new(A, 1),
+                Pitch::new(E, 1),
+                Pitch::new(B, 0),
             ],
-        }
+            20,
+            BASS_SCALE_LENGTH,
+        )
     }
 
     pub fn fret_to_note(&self, string: usize, fret: u8) -> Note {
-        (self.guitar_strings[string].tuning + fret) % 12
+        self.fret_to_pitch(string, fret).note()
+    }
+
+    // Frets are counted from the nut, anything at or below a capo sounds the capo fret
+    pub fn fret_to_pitch(&self, string: usize, fret: u8) -> Pitch {
+        let guitar_string = &self.guitar_strings[string];
+        guitar_string.tuning + fret.max(guitar_string.capo)
+    }
+
+    pub fn set_capo(&mut self, fret: u8) {
+        for guitar_string in &mut self.guitar_strings {
+            guitar_string.capo = fret;
+        }
+    }
+
+    pub fn has_capo(&self) -> bool {
+        self.guitar_strings.iter().any(|s| s.capo > 0)
+    }
+
+    // The first string fretted at or below its capo, where the fret can't be reached
+    pub fn string_under_capo(&self, pattern: &FretPattern) -> Option<usize> {
+        pattern
+            .strings()
+            .iter()
+            .zip(&self.guitar_strings)
+            .position(|(state, s)| matches!(*state, StringState::Fretted(fret) if fret <= s.capo))
+    }
+
+    // The capo fret when it covers all strings
+    pub fn capo(&self) -> Option<u8> {
+        let fret = self.guitar_strings.first()?.capo;
+        (fret > 0 && self.guitar_strings.iter().all(|s| s.capo == fret)).then_some(fret)
+    }
+
+    pub fn without_capo(&self) -> Guitar {
+        let mut guitar = self.clone();
+        guitar.set_capo(0);
+        guitar
+    }
+
+    // The pattern as fingered relative to the capo, to be played on `without_capo`
+    pub fn shape_frets(&self, pattern: &FretPattern) -> FretPattern {
+        let strings = pattern
+            .strings()
+            .iter()
+            .zip(&self.guitar_strings)
+            .map(|(&state, s)| match state {
+                StringState::Fretted(fret) => StringState::from_fret(Some(fret.saturating_sub(s.capo))),
+                _ => state,
+            })
+            .collect();
+        FretPattern::new(strings)
+    }
+
+    pub fn add_string(&mut self, semitones_below: u8, default: Pitch) {
+        let mut guitar_string = GuitarString::new(default);
+        if let Some(last) = self.guitar_strings.last() {
+            guitar_string.tuning = last.tuning - semitones_below;
+            guitar_string.scale_length = last.scale_length;
+        }
+        self.guitar_strings.push(guitar_string);
     }
 
-    pub fn add_string<F>(&mut self, map_prev: F, default: Note)
-    where
-        F: FnOnce(Note) -> Note,
-    {
-        let last_tuning = self.guitar_strings.last().map(|s| s.tuning);
-        let tuning = last_tuning.map(map_prev).unwrap_or(default);
-        self.guitar_strings.push(GuitarString { tuning });
+    // Spreads the scale lengths evenly from the highest string to the lowest, equal lengths for a straight fretboard
+    pub fn set_scale_lengths(&mut self, treble: f32, bass: f32) {
+        let steps = self.guitar_strings.len().saturating_sub(1).max(1) as f32;
+        for (i, guitar_string) in self.guitar_strings.iter_mut().enumerate() {
+            guitar_string.scale_length = treble + (bass - treble) * i as f32 / steps;
+        }
+    }
+
+    pub fn is_multiscale(&self) -> bool {
+        let mut lengths = self.guitar_strings.iter().map(|s| s.scale_length);
+        let first = lengths.next();
+        lengths.any(|length| Some(length) != first)
+    }
+
+    // Position of a fret along the neck relative to the perpendicular fret, so fanned frets can be drawn slanted
+    pub fn fret_position(&self, string: usize, fret: u8) -> f32 {
+        let guitar_string = &self.guitar_strings[string];
+        guitar_string.fret_distance(fret) - guitar_string.fret_distance(self.perpendicular_fret)
+    }
+
+    pub fn chord_voicings(&self, root: Note, chord: &Chord) -> Vec<FretPattern> {
+        let chord_notes = chord
+            .intervals()
+            .iter()
+            .map(|&i| notes_add(root, i))
+            .collect::<Vec<_>>();
+        let mut voicings = Vec::new();
+        for position in 1..=(self.fret_count + 1).saturating_sub(MAX_FRET_SPAN) {
+            let mut strings = Vec::with_capacity(self.guitar_strings.len());
+            self.collect_voicings(position, root, &chord_notes, &mut strings, &mut voicings);
+        }
+
+        let mut seen = HashSet::new();
+        voicings.retain(|v| seen.insert(v.clone()));
+        voicings
+    }
+
+    fn collect_voicings(
+        &self,
+        position: u8,
+        root: Note,
+        chord_notes: &[Note],
+        strings: &mut Vec<StringState>,
+        voicings: &mut Vec<FretPattern>,
+    ) {
+        let string = strings.len();
+        if string == self.guitar_strings.len() {
+            if self.is_voicing_of(strings, root, chord_notes) {
+                voicings.push(FretPattern::new(strings.clone()));
+            }
+            return;
+        }
+
+        let capo = self.guitar_strings[string].capo;
+        let candidates =
+            std::iter::once(0).chain(position.max(capo + 1)..(position + MAX_FRET_SPAN).min(self.fret_count + 1));
+        for fret in candidates {
+            if chord_notes.contains(&self.fret_to_note(string, fret)) {
+                strings.push(StringState::from_fret(Some(fret)));
+                self.collect_voicings(position, root, chord_notes, strings, voicings);
+                strings.pop();
+            }
+        }
+        strings.push(StringState::Muted);
+        self.collect_voicings(position, root, chord_notes, strings, voicings);
+        strings.pop();
+    }
+
+    fn is_voicing_of(&self, strings: &[StringState], root: Note, chord_notes: &[Note]) -> bool {
+        let sounding = |s: usize| strings[s].fret().map(|fret| self.fret_to_note(s, fret));
+        let Some(first) = strings.iter().position(|s| !s.is_muted()) else {
+            return false;
+        };
+        let last = strings.iter().rposition(|s| !s.is_muted()).unwrap();
+        if strings[first..=last].iter().any(|s| s.is_muted()) {
+            return false;
+        }
+        if sounding(last) != Some(root) {
+            return false;
+        }
+        chord_notes
+            .iter()
+            .all(|&note| (first..=last).any(|s| sounding(s) == Some(note)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chord_library::ChordLibrary;
+
+    #[test]
+    fn capo_raises_open_strings_and_keeps_the_shape() {
+        let mut guitar = Guitar::guitar_6_string_standard();
+        guitar.set_capo(2);
+        assert_eq!(guitar.capo(), Some(2));
+        assert_eq!(guitar.fret_to_note(5, 0), FS);
+        assert_eq!(guitar.fret_to_note(5, 1), FS);
+        assert_eq!(guitar.fret_to_note(5, 5), A);
+
+        // G shape, sounds as A
+        let frets = "540005".parse::<FretPattern>().unwrap();
+        assert_eq!(guitar.shape_frets(&frets).to_string(), "320003");
+        assert_eq!(guitar.string_under_capo(&frets), None);
+        assert_eq!(guitar.string_under_capo(&"x02220".parse().unwrap()), Some(1));
+        assert_eq!(guitar.string_under_capo(&"x04442".parse().unwrap()), Some(0));
+
+        let major = ChordLibrary::builtin().find(&[0, 4, 7]).unwrap().clone();
+        let voicings = guitar.chord_voicings(A, &major);
+        assert!(voicings.contains(&frets));
+        assert!(
+            voicings
+                .iter()
+                .flat_map(|v| v.frets())
+                .flatten()
+                .all(|f| f == 0 || f > 2)
+        );
+
+        guitar.guitar_strings[5].capo = 0;
+        assert_eq!(guitar.capo(), None);
+        assert!(guitar.has_capo());
+        assert_eq!(guitar.fret_to_note(5, 0), E);
+    }
+
+    #[test]
+    fn finds_open_and_barre_voicings_within_the_span() {
+        let guitar = Guitar::guitar_6_string_standard();
+        let library = ChordLibrary::builtin();
+        let major = library.find(&[0, 4, 7]).unwrap();
+        let minor = library.find(&[0, 3, 7]).unwrap();
+
+        let c_major = guitar.chord_voicings(C, major);
+        assert!(c_major.contains(&"x32010".parse().unwrap()));
+        assert!(c_major.contains(&"x35553".parse().unwrap()));
+        assert!(guitar.chord_voicings(F, major).contains(&"133211".parse().unwrap()));
+        assert!(guitar.chord_voicings(B, minor).contains(&"x24432".parse().unwrap()));
+
+        // Neighbouring positions find the same open shapes, each must be listed once
+        let unique = c_major.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), c_major.len());
+
+        for voicing in &c_major {
+            let fretted = voicing
+                .frets()
+                .into_iter()
+                .flatten()
+                .filter(|&f| f > 0)
+                .collect::<Vec<_>>();
+            let span = fretted.iter().max().unwrap_or(&0) - fretted.iter().min().unwrap_or(&0);
+            assert!(span < MAX_FRET_SPAN, "{voicing}");
+            let bass = voicing.strings().iter().rposition(|s| !s.is_muted()).unwrap();
+            assert_eq!(guitar.fret_to_note(bass, voicing.fret(bass).unwrap()), C, "{voicing}");
+        }
+    }
+
+    #[test]
+    fn fanned_frets_line_up_at_the_perpendicular_fret() {
+        let mut guitar = Guitar::guitar_6_string_standard();
+        assert!(!guitar.is_multiscale());
+        assert!((guitar.guitar_strings[0].fret_distance(12) - GUITAR_SCALE_LENGTH / 2.0).abs() < 1e-3);
+
+        guitar.set_scale_lengths(648.0, 686.0);
+        guitar.perpendicular_fret = 7;
+        assert!(guitar.is_multiscale());
+        assert_eq!(guitar.guitar_strings[5].scale_length, 686.0);
+        assert_eq!(guitar.fret_position(0, 7), 0.0);
+        assert_eq!(guitar.fret_position(5, 7), 0.0);
+        assert!(guitar.fret_position(5, 0) < guitar.fret_position(0, 0));
     }
 }
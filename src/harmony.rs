@@ -0,0 +1,154 @@
+use crate::chord_library::ChordLibrary;
+use crate::chords::Chord;
+use crate::notes::{Note, SpelledNote, notes_sub, root_spelling};
+use crate::scales::Scale;
+
+const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HarmonicFunction {
+    Tonic,
+    Subdominant,
+    Dominant,
+}
+
+impl HarmonicFunction {
+    pub fn of_degree(degree: usize) -> Self {
+        match degree % 7 {
+            1 | 3 => HarmonicFunction::Subdominant,
+            4 | 6 => HarmonicFunction::Dominant,
+            _ => HarmonicFunction::Tonic,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HarmonicFunction::Tonic => "tonic",
+            HarmonicFunction::Subdominant => "subdominant",
+            HarmonicFunction::Dominant => "dominant",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DiatonicChord<'a> {
+    pub degree: usize,
+    pub root: Note,
+    pub intervals: Vec<u8>,
+    pub chord: Option<&'a Chord>,
+    pub numeral: String,
+    pub function: HarmonicFunction,
+    pub notes: Vec<SpelledNote>,
+}
+
+pub fn diatonic_chords<'a>(
+    tonic: Note,
+    scale: &Scale,
+    sevenths: bool,
+    library: &'a ChordLibrary,
+) -> Vec<DiatonicChord<'a>> {
+    let scale_notes = scale.spell(root_spelling(tonic));
+    if scale_notes.len() != 7 {
+        return vec![];
+    }
+    let size = if sevenths { 4 } else { 3 };
+
+    (0..7)
+        .map(|degree| {
+            let notes = (0..size).map(|i| scale_notes[(degree + 2 * i) % 7]).collect::<Vec<_>>();
+            let root = notes[0].note();
+            let mut intervals = notes.iter().map(|n| notes_sub(n.note(), root)).collect::<Vec<_>>();
+            intervals.sort_unstable();
+            DiatonicChord {
+                degree,
+                root,
+                chord: library.find(&intervals),
+                numeral: roman_numeral(degree, &intervals),
+                function: HarmonicFunction::of_degree(degree),
+                intervals,
+                notes,
+            }
+        })
+        .collect()
+}
+
+pub fn roman_numeral(degree: usize, intervals: &[u8]) -> String {
+    let has = |i| intervals.contains(&i);
+    let minor = has(3) && !has(4);
+    let numeral = if minor {
+        NUMERALS[degree % 7].to_lowercase()
+    } else {
+        NUMERALS[degree % 7].to_string()
+    };
+    let diminished = minor && has(6) && !has(7);
+    let augmented = has(4) && has(8) && !has(7);
+    let quality = if diminished {
+        if has(9) {
+            "°7"
+        } else if has(10) {
+            "ø7"
+        } else if has(11) {
+            "°(maj7)"
+        } else {
+            "°"
+        }
+    } else if augmented {
+        if has(10) {
+            "+7"
+        } else if has(11) {
+            "+(maj7)"
+        } else {
+            "+"
+        }
+    } else if has(10) {
+        "7"
+    } else if has(11) && minor {
+        "(maj7)"
+    } else if has(11) {
+        "maj7"
+    } else {
+        ""
+    };
+    numeral + quality
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::*;
+    use crate::scales::all_scales;
+
+    fn numerals(tonic: Note, scale: &str, sevenths: bool) -> Vec<String> {
+        let scale = all_scales().iter().find(|s| s.name() == scale).unwrap();
+        diatonic_chords(tonic, scale, sevenths, &ChordLibrary::builtin())
+            .into_iter()
+            .map(|c| c.numeral)
+            .collect()
+    }
+
+    #[test]
+    fn major_key_numerals() {
+        assert_eq!(
+            numerals(C, "Major (Ionian)", false),
+            ["I", "ii", "iii", "IV", "V", "vi", "vii°"]
+        );
+        assert_eq!(
+            numerals(C, "Major (Ionian)", true),
+            ["Imaj7", "ii7", "iii7", "IVmaj7", "V7", "vi7", "viiø7"]
+        );
+    }
+
+    #[test]
+    fn harmonic_minor_chords_are_spelled_and_resolved() {
+        let scale = all_scales().iter().find(|s| s.name() == "Harmonic Minor").unwrap();
+        let library = ChordLibrary::builtin();
+        let chords = diatonic_chords(A, scale, true, &library);
+        assert_eq!(
+            chords.iter().map(|c| c.numeral.as_str()).collect::<Vec<_>>(),
+            ["i(maj7)", "iiø7", "III+(maj7)", "iv7", "V7", "VImaj7", "vii°7"]
+        );
+        let vii = chords[6].notes.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(vii, ["G#", "B", "D", "F"]);
+        assert!(chords.iter().all(|c| c.chord.is_some()));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::guitar::Guitar;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Instrument {
+    pub name: String,
+    pub guitar: Guitar,
+}
+
+impl Instrument {
+    pub fn new(name: &str, guitar: Guitar) -> Self {
+        Self {
+            name: name.to_string(),
+            guitar,
+        }
+    }
+}
+
+// Adds the instrument, replacing a saved one of the same name
+pub fn save_instrument(instruments: &mut Vec<Instrument>, instrument: Instrument) {
+    match instruments.iter_mut().find(|i| i.name == instrument.name) {
+        Some(existing) => *existing = instrument,
+        None => instruments.push(instrument),
+    }
+}
+
+pub fn instruments_to_toml(instruments: &[Instrument]) -> Result<String, InstrumentFileError> {
+    let file = InstrumentFile {
+        instruments: instruments.to_vec(),
+    };
+    toml::to_string(&file).map_err(|e| InstrumentFileError::Syntax(e.to_string()))
+}
+
+pub fn instruments_from_toml(source: &str) -> Result<Vec<Instrument>, InstrumentFileError> {
+    let file = toml::from_str::<InstrumentFile>(source).map_err(|e| InstrumentFileError::Syntax(e.to_string()))?;
+    if let Some(instrument) = file.instruments.iter().find(|i| i.guitar.guitar_strings.is_empty()) {
+        return Err(InstrumentFileError::NoStrings {
+            name: instrument.name.clone(),
+        });
+    }
+    Ok(file.instruments)
+}
+
+pub fn export_instruments(path: &Path, instruments: &[Instrument]) -> Result<(), InstrumentFileError> {
+    std::fs::write(path, instruments_to_toml(instruments)?).map_err(|error| InstrumentFileError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+pub fn import_instruments(path: &Path) -> Result<Vec<Instrument>, InstrumentFileError> {
+    let source = std::fs::read_to_string(path).map_err(|error| InstrumentFileError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    instruments_from_toml(&source)
+}
+
+#[derive(Debug)]
+pub enum InstrumentFileError {
+    Io { path: PathBuf, error: std::io::Error },
+    Syntax(String),
+    NoStrings { name: String },
+}
+
+impl fmt::Display for InstrumentFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstrumentFileError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            InstrumentFileError::Syntax(message) => write!(f, "{message}"),
+            InstrumentFileError::NoStrings { name } => write!(f, "instrument '{name}' has no strings"),
+        }
+    }
+}
+
+impl std::error::Error for InstrumentFileError {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InstrumentFile {
+    #[serde(default, rename = "instrument")]
+    instruments: Vec<Instrument>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tunings::all_tunings;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut drop_d = Guitar::guitar_6_string_standard();
+        drop_d.guitar_strings[5].tuning = drop_d.guitar_strings[5].tuning - 2;
+        drop_d.guitar_strings[0].capo = 2;
+        let mut instruments = vec![Instrument::new("My drop D", drop_d)];
+        for tuning in all_tunings() {
+            save_instrument(&mut instruments, Instrument::new(tuning.name(), tuning.guitar()));
+        }
+        save_instrument(
+            &mut instruments,
+            Instrument::new("My drop D", Guitar::bass_4_string_standard()),
+        );
+        assert_eq!(instruments[0].guitar, Guitar::bass_4_string_standard());
+        assert_eq!(instruments.len(), all_tunings().len() + 1);
+
+        let source = instruments_to_toml(&instruments).unwrap();
+        assert!(source.contains("tuning = \"E1\""));
+        assert_eq!(instruments_from_toml(&source).unwrap(), instruments);
+
+        assert!(instruments_from_toml("[[instrument]]\nname = \"x\"").is_err());
+        assert!(
+            instruments_from_toml(
+                "[[instrument]]\nname = \"x\"\n[instrument.guitar]\nguitar_strings = []\nfret_count = 20\nperpendicular_fret = 0"
+            )
+            .is_err()
+        );
+    }
+}
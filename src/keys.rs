@@ -0,0 +1,242 @@
+use crate::harmony::roman_numeral;
+use crate::notes::{CS, FS, GS, Note, SpelledNote, notes_add, notes_sub, root_spelling};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Krumhansl-Kessler probe tone profiles, indexed by semitones above the tonic
+const MAJOR_PROFILE: [f32; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f32; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+const MAJOR_SCALE: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+const NATURAL_MINOR_SCALE: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+const HARMONIC_MINOR_SCALE: [u8; 7] = [0, 2, 3, 5, 7, 8, 11];
+const MELODIC_MINOR_SCALE: [u8; 7] = [0, 2, 3, 5, 7, 9, 11];
+
+// Scale degree and accidental of each chromatic chord root. In minor the raised 6th and 7th of
+// melodic and harmonic minor count as diatonic, so V7 and vii°7 need no accidental
+const MAJOR_ROOT_DEGREES: [(usize, &str); 12] = [
+    (0, ""),
+    (1, "b"),
+    (1, ""),
+    (2, "b"),
+    (2, ""),
+    (3, ""),
+    (3, "#"),
+    (4, ""),
+    (5, "b"),
+    (5, ""),
+    (6, "b"),
+    (6, ""),
+];
+const MINOR_ROOT_DEGREES: [(usize, &str); 12] = [
+    (0, ""),
+    (1, "b"),
+    (1, ""),
+    (2, ""),
+    (2, "#"),
+    (3, ""),
+    (3, "#"),
+    (4, ""),
+    (5, ""),
+    (5, ""),
+    (6, ""),
+    (6, ""),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyMode {
+    Major,
+    Minor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Key {
+    pub tonic: Note,
+    pub mode: KeyMode,
+}
+
+impl Key {
+    pub fn new(tonic: Note, mode: KeyMode) -> Self {
+        Self { tonic, mode }
+    }
+
+    pub fn major(tonic: Note) -> Self {
+        Self::new(tonic, KeyMode::Major)
+    }
+
+    pub fn minor(tonic: Note) -> Self {
+        Self::new(tonic, KeyMode::Minor)
+    }
+
+    pub fn tonic_spelling(&self) -> SpelledNote {
+        // C#, F# and G# minor have fewer accidentals than Db, Gb and Ab minor
+        match (self.mode, self.tonic) {
+            (KeyMode::Minor, CS | FS | GS) => SpelledNote::with_sharp(self.tonic),
+            _ => root_spelling(self.tonic),
+        }
+    }
+
+    pub fn scale(&self) -> [u8; 7] {
+        match self.mode {
+            KeyMode::Major => MAJOR_SCALE,
+            KeyMode::Minor => NATURAL_MINOR_SCALE,
+        }
+    }
+
+    pub fn notes(&self) -> Vec<Note> {
+        self.scale().iter().map(|&i| notes_add(self.tonic, i)).collect()
+    }
+
+    pub fn contains(&self, note: Note) -> bool {
+        self.notes().contains(&note)
+    }
+
+    pub fn is_diatonic(&self, notes: &[Note]) -> bool {
+        let scales: &[[u8; 7]] = match self.mode {
+            KeyMode::Major => &[MAJOR_SCALE],
+            KeyMode::Minor => &[NATURAL_MINOR_SCALE, HARMONIC_MINOR_SCALE, MELODIC_MINOR_SCALE],
+        };
+        scales
+            .iter()
+            .any(|scale| notes.iter().all(|&n| scale.contains(&notes_sub(n, self.tonic))))
+    }
+
+    pub fn parallel(&self) -> Key {
+        match self.mode {
+            KeyMode::Major => Key::minor(self.tonic),
+            KeyMode::Minor => Key::major(self.tonic),
+        }
+    }
+
+    pub fn relative(&self) -> Key {
+        match self.mode {
+            KeyMode::Major => Key::minor(notes_add(self.tonic, 9)),
+            KeyMode::Minor => Key::major(notes_add(self.tonic, 3)),
+        }
+    }
+
+    pub fn degree_of(&self, root: Note) -> (usize, &'static str) {
+        let semitones = notes_sub(root, self.tonic) as usize;
+        match self.mode {
+            KeyMode::Major => MAJOR_ROOT_DEGREES[semitones],
+            KeyMode::Minor => MINOR_ROOT_DEGREES[semitones],
+        }
+    }
+
+    pub fn triad_numeral(&self, degree: usize) -> String {
+        // The dominant and leading-tone chords of a minor key take the raised 7th
+        let scale = match (self.mode, degree % 7) {
+            (KeyMode::Minor, 4 | 6) => HARMONIC_MINOR_SCALE,
+            _ => self.scale(),
+        };
+        let intervals = [0, 2, 4].map(|step| {
+            let interval = scale[(degree + step) % 7] + 12 - scale[degree % 7];
+            interval % 12
+        });
+        roman_numeral(degree, &intervals)
+    }
+
+    pub fn roman_numeral(&self, root: Note, intervals: &[u8]) -> String {
+        let (degree, accidental) = self.degree_of(root);
+        format!("{accidental}{}", roman_numeral(degree, intervals))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            KeyMode::Major => write!(f, "{} major", self.tonic_spelling()),
+            KeyMode::Minor => write!(f, "{} minor", self.tonic_spelling()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEstimate {
+    pub key: Key,
+    pub score: f32,
+}
+
+pub fn detect_key(weights: &[f32; 12]) -> Vec<KeyEstimate> {
+    let mut estimates = [KeyMode::Major, KeyMode::Minor]
+        .into_iter()
+        .flat_map(|mode| (0..12).map(move |tonic| Key::new(tonic, mode)))
+        .filter_map(|key| {
+            let profile = match key.mode {
+                KeyMode::Major => &MAJOR_PROFILE,
+                KeyMode::Minor => &MINOR_PROFILE,
+            };
+            let rotated = (0..12).map(|note| profile[notes_sub(note, key.tonic) as usize]);
+            correlation(weights.iter().copied(), rotated).map(|score| KeyEstimate { key, score })
+        })
+        .collect::<Vec<_>>();
+    estimates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    estimates
+}
+
+pub fn detect_key_from_notes(notes: &[Note]) -> Vec<KeyEstimate> {
+    let mut weights = [0.0; 12];
+    for &note in notes {
+        weights[note as usize % 12] += 1.0;
+    }
+    detect_key(&weights)
+}
+
+pub fn detect_key_from_chords(chords: &[(Note, &[u8])]) -> Vec<KeyEstimate> {
+    let mut weights = [0.0; 12];
+    for &(root, intervals) in chords {
+        // The root gets counted twice, it says more about the key than the other chord tones
+        weights[root as usize % 12] += 1.0;
+        for &interval in intervals {
+            weights[notes_add(root, interval) as usize] += 1.0;
+        }
+    }
+    detect_key(&weights)
+}
+
+fn correlation(xs: impl Iterator<Item = f32> + Clone, ys: impl Iterator<Item = f32> + Clone) -> Option<f32> {
+    let mean_x = xs.clone().sum::<f32>() / 12.0;
+    let mean_y = ys.clone().sum::<f32>() / 12.0;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.zip(ys) {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x) * (x - mean_x);
+        variance_y += (y - mean_y) * (y - mean_y);
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::*;
+
+    #[test]
+    fn detects_keys_from_progressions() {
+        let major: &[u8] = &[0, 4, 7];
+        let minor: &[u8] = &[0, 3, 7];
+        let dominant: &[u8] = &[0, 4, 7, 10];
+
+        let g_major = detect_key_from_chords(&[(G, major), (C, major), (D, dominant), (E, minor), (G, major)]);
+        assert_eq!(g_major[0].key, Key::major(G));
+
+        let a_minor = detect_key_from_chords(&[(A, minor), (D, minor), (E, dominant), (A, minor)]);
+        assert_eq!(a_minor[0].key, Key::minor(A));
+        assert_eq!(a_minor.len(), 24);
+
+        assert!(detect_key_from_notes(&[]).is_empty());
+    }
+
+    #[test]
+    fn numerals_relative_to_a_key() {
+        assert_eq!(Key::major(G).roman_numeral(D, &[0, 4, 7, 10]), "V7");
+        assert_eq!(Key::major(C).roman_numeral(AS, &[0, 4, 7]), "bVII");
+        assert_eq!(Key::minor(A).roman_numeral(GS, &[0, 3, 6, 9]), "vii°7");
+        assert_eq!(Key::minor(A).roman_numeral(AS, &[0, 4, 7]), "bII");
+        assert_eq!(Key::minor(GS).to_string(), "G# minor");
+        assert_eq!(Key::major(GS).to_string(), "Ab major");
+    }
+}
@@ -1,4 +1,18 @@
+pub mod analysis;
 pub mod audio;
+pub mod chord_library;
+pub mod chord_symbols;
 pub mod chords;
+pub mod fingering;
+pub mod fret_pattern;
+pub mod fretboard;
 pub mod guitar;
+pub mod harmony;
+pub mod instruments;
+pub mod keys;
 pub mod notes;
+pub mod progression;
+pub mod scales;
+pub mod temperament;
+pub mod tunings;
+pub mod voice_leading;
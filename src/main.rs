@@ -3,14 +3,29 @@
 use cpal::traits::StreamTrait;
 use eframe::egui;
 use eframe::egui::Ui;
-use guitar_chords_egui_v1::audio::{
-    play_frequency_sawtooth, play_frequency_sine_plus_harmonics, play_frequency_sine_wave,
-    playe_frequency_karplus_strong,
-};
-use guitar_chords_egui_v1::chords::possible_chords;
-use guitar_chords_egui_v1::guitar::Guitar;
+use guitar_chords_egui_v1::analysis::analyze_progression;
+use guitar_chords_egui_v1::audio::{play_karplus_strong, play_sawtooth, play_sine_plus_harmonics, play_sine_wave};
+use guitar_chords_egui_v1::chord_library::ChordLibrary;
+use guitar_chords_egui_v1::chord_symbols::parse_chord_symbol;
+use guitar_chords_egui_v1::chords::{Chord, ChordMatch, MatchOptions};
+use guitar_chords_egui_v1::fingering::{Barre, Fingering, fingering};
+use guitar_chords_egui_v1::fret_pattern::{FretPattern, StringState};
+use guitar_chords_egui_v1::fretboard::{Dot, Fretboard, Orientation, diagram_frets, interval_color};
+use guitar_chords_egui_v1::guitar::{GUITAR_SCALE_LENGTH, Guitar, MAX_FRET_SPAN};
+use guitar_chords_egui_v1::harmony::diatonic_chords;
+use guitar_chords_egui_v1::instruments::{Instrument, export_instruments, import_instruments, save_instrument};
+use guitar_chords_egui_v1::keys::{Key, KeyMode, detect_key_from_chords, detect_key_from_notes};
 use guitar_chords_egui_v1::notes::*;
-use std::collections::HashSet;
+use guitar_chords_egui_v1::progression::{Progression, ProgressionChord};
+use guitar_chords_egui_v1::scales::{Scale, all_scales};
+use guitar_chords_egui_v1::temperament::{Intonation, TEMPERAMENTS, Temperament};
+use guitar_chords_egui_v1::tunings::{all_tunings, find_tuning};
+use guitar_chords_egui_v1::voice_leading::{VoiceLeadingError, VoiceLeadingOptions, optimize_voice_leading};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 fn main() -> eframe::Result {
     env_logger::init();
@@ -21,30 +36,168 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Guitar Chords",
         options,
-        Box::new(|_cc| Ok(Box::<GuitarChordsApp>::default())),
+        Box::new(|cc| {
+            let mut app = GuitarChordsApp::default();
+            if let Some(state) = cc.storage.and_then(load_state) {
+                app.apply_state(state);
+            }
+            if Path::new(&app.chord_library_path).exists() {
+                app.load_chord_library();
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
 
 struct GuitarChordsApp {
     guitar: Guitar,
-    frets_selected: Vec<Option<u8>>,
+    frets_selected: FretPattern,
+    frets_input: String,
+    frets_input_error: Option<String>,
     selected_tab: GuitarChordsTabs,
     audio_streams: Vec<cpal::Stream>,
+    finder_root: Note,
+    finder_chord: usize,
+    finder_symbol: String,
+    finder_symbol_error: Option<String>,
+    scale_root: Note,
+    scale: usize,
+    diatonic_sevenths: bool,
+    chord_library: Rc<ChordLibrary>,
+    chord_library_path: String,
+    chord_library_error: Option<String>,
+    match_options: MatchOptions,
+    identifier_key: Option<Key>,
+    intonation: Intonation,
+    playback_octave_from: i8,
+    playback_octave_to: i8,
+    volume: f32,
+    progressions: Vec<Progression>,
+    progression: usize,
+    progression_input: String,
+    progression_input_error: Option<String>,
+    progression_loop: bool,
+    progression_playback: Option<ProgressionPlayback>,
+    voicing_cache: VoicingCache,
+    voice_leading_options: VoiceLeadingOptions,
+    voice_leading_report: Vec<String>,
+    voice_leading_error: Option<String>,
+    tuning_search: String,
+    fretboard_orientation: Orientation,
+    instruments: Vec<Instrument>,
+    instrument_name: String,
+    instruments_path: String,
+    instruments_error: Option<String>,
+}
+
+fn voicing_notes(guitar: &Guitar, pattern: &FretPattern) -> Vec<Note> {
+    let mut pitches = pattern
+        .strings()
+        .iter()
+        .enumerate()
+        .filter_map(|(s, state)| state.fret().map(|f| guitar.fret_to_pitch(s, f)))
+        .collect::<Vec<_>>();
+    pitches.sort_unstable();
+    pitches.into_iter().map(|p| p.note()).collect()
+}
+
+struct ProgressionPlayback {
+    started: Instant,
+    // Loop pass and chord index being played, a looping single chord is struck again each pass
+    chord: Option<(u32, usize)>,
+    streams: Vec<cpal::Stream>,
+}
+
+// Voicings with their fingerings, most playable first
+type ScoredVoicings = Rc<Vec<(FretPattern, Fingering)>>;
+
+// Voicings of chords on the current guitar, searching and fingering them every frame is too slow
+#[derive(Default)]
+struct VoicingCache {
+    guitar: Option<Guitar>,
+    voicings: HashMap<(Note, Vec<u8>), ScoredVoicings>,
+}
+
+impl VoicingCache {
+    fn get(&mut self, guitar: &Guitar, root: Note, chord: &Chord) -> ScoredVoicings {
+        if self.guitar.as_ref() != Some(guitar) {
+            self.guitar = Some(guitar.clone());
+            self.voicings.clear();
+        }
+        self.voicings
+            .entry((root, chord.intervals().to_vec()))
+            .or_insert_with(|| {
+                let mut voicings = guitar
+                    .chord_voicings(root, chord)
+                    .into_iter()
+                    .map(|voicing| {
+                        let fingering = fingering(&voicing);
+                        (voicing, fingering)
+                    })
+                    .collect::<Vec<_>>();
+                voicings.sort_by(|(_, a), (_, b)| b.score.total_cmp(&a.score));
+                Rc::new(voicings)
+            })
+            .clone()
+    }
 }
 
 impl Default for GuitarChordsApp {
     fn default() -> Self {
         Self {
             guitar: Guitar::guitar_6_string_standard(),
-            frets_selected: vec![None; 6],
+            frets_selected: FretPattern::muted(6),
+            frets_input: String::new(),
+            frets_input_error: None,
             selected_tab: GuitarChordsTabs::ChordIdentifier,
             audio_streams: vec![],
+            finder_root: C,
+            finder_chord: 0,
+            finder_symbol: String::new(),
+            finder_symbol_error: None,
+            scale_root: C,
+            scale: 0,
+            diatonic_sevenths: false,
+            chord_library: Rc::new(ChordLibrary::builtin()),
+            chord_library_path: "chords.toml".to_string(),
+            chord_library_error: None,
+            match_options: MatchOptions::default(),
+            identifier_key: None,
+            intonation: Intonation::default(),
+            playback_octave_from: 4,
+            playback_octave_to: 4,
+            volume: 1.0,
+            progressions: vec![default_progression()],
+            progression: 0,
+            progression_input: String::new(),
+            progression_input_error: None,
+            progression_loop: false,
+            progression_playback: None,
+            voicing_cache: VoicingCache::default(),
+            voice_leading_options: VoiceLeadingOptions::default(),
+            voice_leading_report: vec![],
+            voice_leading_error: None,
+            tuning_search: String::new(),
+            fretboard_orientation: Orientation::default(),
+            instruments: vec![],
+            instrument_name: String::new(),
+            instruments_path: "instruments.toml".to_string(),
+            instruments_error: None,
         }
     }
 }
 
+fn default_progression() -> Progression {
+    let mut progression = Progression::new("ii-V-I in Bb");
+    progression.key = Key::major(AS);
+    progression.push_symbols("Cm7 F7 Bbmaj7 %").unwrap();
+    progression
+}
+
 impl eframe::App for GuitarChordsApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.advance_progression_playback(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Guitar Chords");
             ui.separator();
@@ -55,12 +208,77 @@ impl eframe::App for GuitarChordsApp {
                 GuitarChordsTabs::ChordIdentifier => self.chord_identifier(ui),
                 GuitarChordsTabs::AudioPlayback => self.audio_playback(ui),
                 GuitarChordsTabs::ChordFinder => self.chord_finder(ui),
+                GuitarChordsTabs::ScaleExplorer => self.scale_explorer(ui),
+                GuitarChordsTabs::ProgressionEditor => self.progression_editor(ui),
             }
         });
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STATE_KEY, &self.saved_state());
+    }
 }
 
 impl GuitarChordsApp {
+    fn saved_state(&self) -> SavedState {
+        SavedState {
+            version: STATE_VERSION,
+            guitar: self.guitar.clone(),
+            frets_selected: self.frets_selected.clone(),
+            selected_tab: self.selected_tab,
+            intonation: self.intonation.clone(),
+            playback_octave_from: self.playback_octave_from,
+            playback_octave_to: self.playback_octave_to,
+            volume: self.volume,
+            match_options: self.match_options,
+            identifier_key: self.identifier_key,
+            finder_root: self.finder_root,
+            finder_chord: self.finder_chord,
+            scale_root: self.scale_root,
+            scale: self.scale,
+            diatonic_sevenths: self.diatonic_sevenths,
+            chord_library_path: self.chord_library_path.clone(),
+            instruments: self.instruments.clone(),
+            progressions: self.progressions.clone(),
+            progression: self.progression,
+            progression_loop: self.progression_loop,
+            voice_leading_options: self.voice_leading_options,
+            fretboard_orientation: self.fretboard_orientation,
+        }
+    }
+
+    fn apply_state(&mut self, state: SavedState) {
+        if !state.guitar.guitar_strings.is_empty() {
+            self.frets_selected = match state.frets_selected.len() == state.guitar.guitar_strings.len() {
+                true => state.frets_selected,
+                false => FretPattern::muted(state.guitar.guitar_strings.len()),
+            };
+            self.guitar = state.guitar;
+        }
+        self.selected_tab = state.selected_tab;
+        self.intonation = state.intonation;
+        self.playback_octave_from = state.playback_octave_from;
+        self.playback_octave_to = state.playback_octave_to.max(state.playback_octave_from);
+        self.volume = state.volume.clamp(0.0, 1.0);
+        self.match_options = state.match_options;
+        self.identifier_key = state.identifier_key;
+        self.finder_root = state.finder_root % 12;
+        // Clamped against the chord library once the finder draws
+        self.finder_chord = state.finder_chord;
+        self.scale_root = state.scale_root % 12;
+        self.scale = state.scale.min(all_scales().len() - 1);
+        self.diatonic_sevenths = state.diatonic_sevenths;
+        self.chord_library_path = state.chord_library_path;
+        self.instruments = state.instruments;
+        if !state.progressions.is_empty() {
+            self.progression = state.progression.min(state.progressions.len() - 1);
+            self.progressions = state.progressions;
+        }
+        self.progression_loop = state.progression_loop;
+        self.voice_leading_options = state.voice_leading_options;
+        self.fretboard_orientation = state.fretboard_orientation;
+    }
+
     fn tabs(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
             let chord_identifier_tab = ui.selectable_label(
@@ -86,6 +304,22 @@ impl GuitarChordsApp {
             if chord_finder_tab.clicked() {
                 self.selected_tab = GuitarChordsTabs::ChordFinder;
             }
+
+            ui.separator();
+
+            let scale_explorer_tab =
+                ui.selectable_label(self.selected_tab == GuitarChordsTabs::ScaleExplorer, "Scale Explorer");
+            if scale_explorer_tab.clicked() {
+                self.selected_tab = GuitarChordsTabs::ScaleExplorer;
+            }
+
+            ui.separator();
+
+            let progression_editor_tab =
+                ui.selectable_label(self.selected_tab == GuitarChordsTabs::ProgressionEditor, "Progressions");
+            if progression_editor_tab.clicked() {
+                self.selected_tab = GuitarChordsTabs::ProgressionEditor;
+            }
         });
     }
 
@@ -93,214 +327,1418 @@ impl GuitarChordsApp {
         self.pick_guitar(ui);
         ui.separator();
 
-        for string in 0..self.guitar.guitar_strings.len() {
-            if string >= self.guitar.guitar_strings.len() {
-                break;
-            }
-            ui.horizontal(|ui| {
-                ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+        // Matched once per frame, a change made further down shows on the repaint requested at the end
+        let identified = (
+            self.guitar.clone(),
+            self.frets_selected.clone(),
+            self.match_options,
+            self.identifier_key,
+        );
+        let library = self.chord_library.clone();
+        let notes = self.selected_notes();
+        let keys = detect_key_from_notes(&notes);
+        let key = self.identifier_key.or(keys.first().map(|estimate| estimate.key));
+        let chord_matches = self.identified_chords(&library, key);
+        let selected_fingering = fingering(&self.frets_selected);
 
-                if ui.button("X").clicked() {
-                    self.guitar.guitar_strings.remove(string);
-                    self.frets_selected.remove(string);
-                    return;
-                }
-
-                if ui.button("-").clicked() {
-                    let s = self.guitar.guitar_strings[string].tuning;
-                    self.guitar.guitar_strings[string].tuning = notes_sub(s, 1);
-                }
-
-                let tuning = &mut self.guitar.guitar_strings[string].tuning;
+        // Colored by interval from the best chord match, or from the bass note without a match
+        let root = chord_matches
+            .first()
+            .map(|m| m.root)
+            .or(self.selected_voicing().first().copied());
+        let dots = self
+            .frets_selected
+            .strings()
+            .iter()
+            .enumerate()
+            .filter_map(|(string, state)| {
+                let fret = state.fret()?;
+                let note = self.guitar.fret_to_note(string, fret);
+                let text = match chord_matches.first() {
+                    Some(chord_match) => chord_match.spell_note(note).to_string(),
+                    None => note_name(note),
+                };
+                let color = interval_color(notes_sub(note, root.unwrap_or(note)));
+                Some(Dot::new(string, fret, text, color).finger(selected_fingering.fingers[string]))
+            })
+            .collect();
 
-                egui::ComboBox::from_id_salt(string)
-                    .selected_text(&note_name(*tuning))
-                    .width(40.0)
-                    .show_ui(ui, |ui| {
-                        for note in 0..12 {
-                            ui.selectable_value(tuning, note, note_name(note));
+        let strings = self.guitar.guitar_strings.len();
+        match self.fretboard_orientation {
+            Orientation::Horizontal => {
+                // One row of controls per string, as tall as the string spacing
+                let string_spacing = ui.spacing().interact_size.y + ui.spacing().item_spacing.y;
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        for string in 0..strings {
+                            if ui.horizontal(|ui| self.string_controls(ui, string)).inner {
+                                break;
+                            }
                         }
                     });
-
-                if ui.button("+").clicked() {
-                    let s = self.guitar.guitar_strings[string].tuning;
-                    self.guitar.guitar_strings[string].tuning = notes_add(s, 1);
-                }
-
-                for fret in 0..=15 {
-                    if fret > 0 {
-                        ui.separator();
-                    }
-                    let selected_fret = self.frets_selected.get_mut(string).unwrap();
-                    let sl = ui.selectable_label(
-                        selected_fret.is_some_and(|v| v == fret),
-                        note_button_label(self.guitar.fret_to_note(string, fret)),
-                    );
-                    if sl.clicked() {
-                        if selected_fret.is_some_and(|v| v == fret) {
-                            *selected_fret = None;
-                        } else {
-                            *selected_fret = Some(fret);
+                    self.identifier_fretboard(ui, string_spacing, dots, &selected_fingering.barres);
+                });
+            }
+            Orientation::Vertical => {
+                // One column of controls above each string, lowest string on the left
+                let fretboard = Fretboard::new(&self.guitar)
+                    .orientation(Orientation::Vertical)
+                    .string_spacing(VERTICAL_STRING_SPACING);
+                let offsets = (0..strings).map(|s| fretboard.string_offset(s)).collect::<Vec<_>>();
+                ui.horizontal_top(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    let mut left = 0.0;
+                    for string in (0..strings).rev() {
+                        let column_left = offsets[string] - VERTICAL_STRING_SPACING / 2.0;
+                        ui.add_space(column_left - left);
+                        left = column_left + VERTICAL_STRING_SPACING;
+                        let layout = egui::Layout::left_to_right(egui::Align::Center).with_main_wrap(true);
+                        let column =
+                            ui.allocate_ui_with_layout(egui::vec2(VERTICAL_STRING_SPACING, 0.0), layout, |ui| {
+                                ui.set_width(VERTICAL_STRING_SPACING);
+                                ui.spacing_mut().item_spacing.x = 2.0;
+                                self.string_controls(ui, string)
+                            });
+                        if column.inner {
+                            break;
                         }
                     }
-                }
-            });
+                });
+                self.identifier_fretboard(ui, VERTICAL_STRING_SPACING, dots, &selected_fingering.barres);
+            }
         }
 
         ui.separator();
 
         ui.horizontal(|ui| {
             if ui.button("Add string (fifth)").clicked() {
-                self.guitar.add_string(note_fifth, E);
-                self.frets_selected.push(None);
+                self.guitar.add_string(7, Pitch::new(E, 2));
+                self.frets_selected.push(StringState::Muted);
             }
 
             if ui.button("Add string (fourth)").clicked() {
-                self.guitar.add_string(note_fourth, A);
-                self.frets_selected.push(None);
+                self.guitar.add_string(5, Pitch::new(A, 2));
+                self.frets_selected.push(StringState::Muted);
             }
         });
 
         ui.separator();
 
+        let notes_display = match chord_matches.first() {
+            Some(chord_match) => notes
+                .iter()
+                .map(|&n| chord_match.spell_note(n).to_string())
+                .collect::<Vec<_>>(),
+            None => notes.iter().map(|&n| note_name(n)).collect::<Vec<_>>(),
+        };
+        ui.label(format!("Selected notes: {notes_display:?}"));
+        self.edit_frets_pattern(ui);
+        if let Some(names) = self.capo_chord_names(&library, &self.frets_selected) {
+            ui.label(format!("With capo: {names}"));
+        }
+        match &selected_fingering.problem {
+            Some(problem) => ui.colored_label(ui.visuals().error_fg_color, format!("Unplayable: {problem}")),
+            None => ui.label(format!(
+                "Fingering: {}, playability {:.0}%",
+                selected_fingering.label(&self.frets_selected),
+                selected_fingering.score * 100.0
+            )),
+        };
+        if ui.button("Play chord").clicked() {
+            let streams = self.play_frets(&self.frets_selected);
+            self.audio_streams.extend(streams);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Allow omitted:");
+            ui.checkbox(&mut self.match_options.allow_omitted_root, "root");
+            ui.checkbox(&mut self.match_options.allow_omitted_fifth, "5th");
+            ui.checkbox(&mut self.match_options.allow_omitted_ninth, "9th");
+            ui.checkbox(&mut self.match_options.allow_omitted_eleventh, "11th");
+            ui.label("Extra tones:");
+            ui.add(egui::DragValue::new(&mut self.match_options.max_extra_tones).range(0..=3));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Key:");
+            let detected = match keys.first() {
+                Some(estimate) => format!("Detected ({})", estimate.key),
+                None => "Detected".to_string(),
+            };
+            egui::ComboBox::from_id_salt("identifier_key")
+                .selected_text(self.identifier_key.map_or(detected.clone(), |k| k.to_string()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.identifier_key, None, detected);
+                    for mode in [KeyMode::Major, KeyMode::Minor] {
+                        for tonic in 0..12 {
+                            let key = Key::new(tonic, mode);
+                            ui.selectable_value(&mut self.identifier_key, Some(key), key.to_string());
+                        }
+                    }
+                });
+            let likely = keys
+                .iter()
+                .take(MAX_LIKELY_KEYS)
+                .map(|estimate| format!("{} {:.0}%", estimate.key, estimate.score * 100.0))
+                .collect::<Vec<_>>();
+            if !likely.is_empty() {
+                ui.label(format!("Likely keys: {}", likely.join(", ")));
+            }
+        });
+
+        ui.label("Selected chord:");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for chord_match in chord_matches.iter().take(MAX_CHORD_MATCHES) {
+                let tones = chord_match.tones().iter().map(|t| t.to_string()).collect::<Vec<_>>();
+                let mut text = format!(
+                    "{}, {} ({}) - {:.0}%",
+                    chord_match.description(),
+                    chord_match.chord.name(),
+                    tones.join(" "),
+                    chord_match.score * 100.0
+                );
+                if let Some(key) = key {
+                    let numeral = key.roman_numeral(chord_match.root, chord_match.chord.intervals());
+                    text += &format!(" - {numeral} in {key}");
+                }
+                if ui.link(text).clicked() {
+                    self.finder_root = chord_match.root;
+                    self.finder_chord = library.position(chord_match.chord).unwrap();
+                    self.selected_tab = GuitarChordsTabs::ChordFinder;
+                }
+            }
+        });
+
+        if identified
+            != (
+                self.guitar.clone(),
+                self.frets_selected.clone(),
+                self.match_options,
+                self.identifier_key,
+            )
+        {
+            ui.ctx().request_repaint();
+        }
+    }
+
+    // Shows the selection in "x32010" notation and takes a typed pattern for the same number of strings
+    fn edit_frets_pattern(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Pattern:");
+            let id = ui.make_persistent_id("frets_input");
+            if !ui.memory(|m| m.has_focus(id)) {
+                self.frets_input = self.frets_selected.to_string();
+            }
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.frets_input)
+                    .id(id)
+                    .desired_width(120.0),
+            );
+            if input.changed() {
+                match self.frets_input.parse::<FretPattern>() {
+                    Ok(pattern) if pattern.len() != self.guitar.guitar_strings.len() => {
+                        self.frets_input_error = Some(format!(
+                            "{} strings, the instrument has {}",
+                            pattern.len(),
+                            self.guitar.guitar_strings.len()
+                        ))
+                    }
+                    Ok(pattern) => {
+                        // Like clicks on the fretboard, frets at or below a capo can't be picked
+                        match self.guitar.string_under_capo(&pattern) {
+                            Some(string) => {
+                                self.frets_input_error = Some(format!(
+                                    "fret {} of the {} string is under the capo",
+                                    pattern.fret(string).unwrap(),
+                                    self.guitar.guitar_strings[string].tuning
+                                ))
+                            }
+                            None => {
+                                self.frets_selected = pattern;
+                                self.frets_selected.clamp(self.guitar.fret_count);
+                                self.frets_input_error = None;
+                            }
+                        }
+                    }
+                    Err(e) => self.frets_input_error = Some(e.to_string()),
+                }
+            }
+            if input.lost_focus() {
+                self.frets_input_error = None;
+            }
+            if let Some(error) = &self.frets_input_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+    }
+
+    fn play_frets(&self, pattern: &FretPattern) -> Vec<cpal::Stream> {
+        pattern
+            .strings()
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(string, state)| state.fret().map(|f| self.guitar.fret_to_pitch(string, f)))
+            .map(|pitch| play_karplus_strong(pitch, &self.intonation, self.volume))
+            .collect()
+    }
+
+    // Spelled in the chosen or detected key, so G#m reads as G#m in E major
+    fn identified_chords<'a>(&self, library: &'a ChordLibrary, key: Option<Key>) -> Vec<ChordMatch<'a>> {
+        let chord_matches = library.identify_voicing(&self.selected_voicing(), &self.match_options);
+        match key {
+            Some(key) => chord_matches
+                .into_iter()
+                .map(|m| m.in_key(key.tonic_spelling()))
+                .collect(),
+            None => chord_matches,
+        }
+    }
+
+    fn selected_voicing(&self) -> Vec<Note> {
+        voicing_notes(&self.guitar, &self.frets_selected)
+    }
+
+    // Returns true when the string was removed
+    fn string_controls(&mut self, ui: &mut Ui, string: usize) -> bool {
+        ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+
+        if ui.button("X").clicked() {
+            self.guitar.guitar_strings.remove(string);
+            self.frets_selected.remove(string);
+            return true;
+        }
+
+        if ui.button("-").clicked() {
+            let s = self.guitar.guitar_strings[string].tuning;
+            self.guitar.guitar_strings[string].tuning = s - 1;
+        }
+
+        let tuning = &mut self.guitar.guitar_strings[string].tuning;
+
+        egui::ComboBox::from_id_salt(string)
+            .selected_text(tuning.to_string())
+            .width(40.0)
+            .show_ui(ui, |ui| {
+                for octave in 0..=6 {
+                    for note in 0..12 {
+                        let pitch = Pitch::new(note, octave);
+                        ui.selectable_value(tuning, pitch, pitch.to_string());
+                    }
+                }
+            });
+
+        if ui.button("+").clicked() {
+            let s = self.guitar.guitar_strings[string].tuning;
+            self.guitar.guitar_strings[string].tuning = s + 1;
+        }
+
+        let fret_count = self.guitar.fret_count;
+        let capo = &mut self.guitar.guitar_strings[string].capo;
+        if ui
+            .add(egui::DragValue::new(capo).range(0..=fret_count).prefix("capo "))
+            .changed()
+        {
+            self.mute_frets_under_capo();
+        }
+        false
+    }
+
+    // A raised capo covers frets that could be selected before it moved
+    fn mute_frets_under_capo(&mut self) {
+        while let Some(string) = self.guitar.string_under_capo(&self.frets_selected) {
+            self.frets_selected.set(string, StringState::Muted);
+        }
+    }
+
+    fn identifier_fretboard(&mut self, ui: &mut Ui, string_spacing: f32, dots: Vec<Dot>, barres: &[Barre]) {
+        egui::ScrollArea::both()
+            .id_salt("identifier_fretboard")
+            .max_height(FRETBOARD_MAX_HEIGHT)
+            .show(ui, |ui| {
+                let fretboard = Fretboard::new(&self.guitar)
+                    .orientation(self.fretboard_orientation)
+                    .string_spacing(string_spacing)
+                    .voicing(&self.frets_selected)
+                    .barres(barres)
+                    .dots(dots)
+                    .hover_preview(true)
+                    .show(ui);
+                if let Some((string, fret)) = fretboard.hovered {
+                    let pitch = self.guitar.fret_to_pitch(string, fret);
+                    fretboard.response.on_hover_text(format!("{pitch}, fret {fret}"));
+                }
+                if let Some((string, fret)) = fretboard.clicked {
+                    self.frets_selected.toggle(string, fret);
+                }
+            });
+    }
+
+    // "G shape, sounds as A" when a capo is on, None without one
+    fn capo_chord_names(&self, library: &ChordLibrary, frets: &FretPattern) -> Option<String> {
+        if !self.guitar.has_capo() {
+            return None;
+        }
+        let shape = voicing_notes(&self.guitar.without_capo(), &self.guitar.shape_frets(frets));
+        let shape_name = library
+            .identify_voicing(&shape, &self.match_options)
+            .first()?
+            .short_name();
+        let sounding = voicing_notes(&self.guitar, frets);
+        let sounding_name = library
+            .identify_voicing(&sounding, &self.match_options)
+            .first()?
+            .short_name();
+        Some(format!("{shape_name} shape, sounds as {sounding_name}"))
+    }
+
+    fn selected_notes(&self) -> Vec<Note> {
         let notes = self
             .frets_selected
+            .strings()
             .iter()
             .enumerate()
-            .filter(|(_, f)| f.is_some())
-            .map(|(s, f)| self.guitar.fret_to_note(s, f.unwrap()))
+            .filter_map(|(s, state)| state.fret().map(|f| self.guitar.fret_to_note(s, f)))
             .collect::<HashSet<_>>();
         let mut notes = notes.into_iter().collect::<Vec<_>>();
         notes.sort_unstable();
-        let notes_display = notes.iter().map(|&n| note_name(n)).collect::<Vec<_>>();
-        ui.label(format!("Selected notes: {notes_display:?}"));
-        // TODO make the chord clickable, leading to chord finder tab
-        ui.label(format!("Selected chord: {:#?}", possible_chords(&notes)));
+        notes
     }
 
     fn audio_playback(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("A4 reference:");
+            ui.add(
+                egui::DragValue::new(&mut self.intonation.a4_frequency)
+                    .range(400.0..=480.0)
+                    .suffix(" Hz"),
+            );
+            for (name, frequency) in A4_REFERENCES {
+                if ui
+                    .selectable_label(self.intonation.a4_frequency == frequency, name)
+                    .clicked()
+                {
+                    self.intonation.a4_frequency = frequency;
+                }
+            }
+        });
+
+        self.pick_temperament(ui);
+
+        ui.horizontal(|ui| {
+            ui.label("Octaves:");
+            ui.add(egui::DragValue::new(&mut self.playback_octave_from).range(0..=8));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut self.playback_octave_to).range(self.playback_octave_from..=8));
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Volume:");
+            ui.add(egui::Slider::new(&mut self.volume, 0.0..=1.0));
+        });
+
+        ui.separator();
         ui.label("Note playback:");
 
-        // TODO calculate the frequencies, A3 is 220hz:
-        //  To calculate the frequency of a musical note, you can use a formula that relates it to a
-        //  reference frequency (usually A4 at 440 Hz) and the number of semitones away from that reference.
-        //  The formula is: f = 2^(n/12) * 440, where 'f' is the frequency you want to calculate,
-        //  and 'n' is the number of semitones above (positive) or below (negative) the reference note A4.
-        let notes = vec![
-            ("C4", 261.63),
-            ("C#4", 277.18),
-            ("D4", 293.66),
-            ("D#4", 311.13),
-            ("E4", 329.63),
-            ("F4", 349.23),
-            ("F#4", 369.99),
-            ("G4", 392.00),
-            ("G#4", 415.30),
-            ("A4", 440.00),
-            ("A#4", 466.16),
-            ("B4", 493.88),
+        let pitches = (self.playback_octave_from..=self.playback_octave_to)
+            .flat_map(|octave| (0..12).map(move |note| Pitch::new(note, octave)))
+            .collect::<Vec<_>>();
+        let generators: [(&str, PlayPitch); 4] = [
+            ("Sine waves:", play_sine_wave),
+            ("Sine waves plus harmonics:", play_sine_plus_harmonics),
+            ("Sawtooth:", play_sawtooth),
+            ("Karplus-Strong:", play_karplus_strong),
         ];
 
-        // TODO common volume
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for (label, play) in generators {
+                ui.label(label);
+                ui.horizontal_wrapped(|ui| {
+                    for pitch in &pitches {
+                        if ui.button(format!("Play {pitch}")).clicked() {
+                            self.audio_streams.push(play(*pitch, &self.intonation, self.volume));
+                        }
+                    }
+                });
+            }
+        });
+
+        ui.separator();
 
-        ui.label("Sine waves:");
         ui.horizontal(|ui| {
-            for (note, freq) in &notes {
-                if ui.button("Play ".to_string() + note).clicked() {
-                    self.audio_streams.push(play_frequency_sine_wave(*freq));
+            if ui.button("Stop all").clicked() {
+                self.audio_streams.clear();
+            }
+
+            if ui.button("Pause all").clicked() {
+                for stream in self.audio_streams.iter_mut() {
+                    stream.pause().expect("pause failed");
+                }
+            }
+
+            if ui.button("Un-Pause all").clicked() {
+                for stream in self.audio_streams.iter_mut() {
+                    stream.play().expect("play failed");
                 }
             }
         });
+        // TODO
+    }
+
+    fn chord_finder(&mut self, ui: &mut Ui) {
+        self.pick_guitar(ui);
+        ui.separator();
+
+        let library = self.chord_library.clone();
+        let Some(last_chord) = library.chords().len().checked_sub(1) else {
+            self.chord_library_file(ui);
+            ui.label("The chord library is empty.");
+            return;
+        };
+        self.finder_chord = self.finder_chord.min(last_chord);
 
-        ui.label("Sine waves plus harmonics:");
         ui.horizontal(|ui| {
-            for (note, freq) in &notes {
-                if ui.button("Play ".to_string() + note).clicked() {
-                    self.audio_streams.push(play_frequency_sine_plus_harmonics(*freq));
+            ui.label("Root:");
+            egui::ComboBox::from_id_salt("finder_root")
+                .selected_text(root_name(self.finder_root))
+                .width(40.0)
+                .show_ui(ui, |ui| {
+                    for note in 0..12 {
+                        ui.selectable_value(&mut self.finder_root, note, root_name(note));
+                    }
+                });
+
+            ui.label("Chord:");
+            egui::ComboBox::from_id_salt("finder_chord")
+                .selected_text(library.chords()[self.finder_chord].name())
+                .show_ui(ui, |ui| {
+                    for (i, chord) in library.chords().iter().enumerate() {
+                        ui.selectable_value(&mut self.finder_chord, i, chord.name());
+                    }
+                });
+
+            ui.separator();
+
+            ui.label("Symbol:");
+            let symbol_input = ui.add(egui::TextEdit::singleline(&mut self.finder_symbol).desired_width(100.0));
+            if symbol_input.changed() && !self.finder_symbol.trim().is_empty() {
+                match parse_chord_symbol(&self.finder_symbol) {
+                    Ok(symbol) => match symbol.chord(&library) {
+                        Some(chord) => {
+                            self.finder_root = symbol.root.note();
+                            self.finder_chord = library.position(chord).unwrap();
+                            self.finder_symbol_error = None;
+                        }
+                        None => self.finder_symbol_error = Some("no such chord in the dictionary".to_string()),
+                    },
+                    Err(e) => self.finder_symbol_error = Some(e.to_string()),
                 }
             }
+            if let Some(error) = &self.finder_symbol_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
         });
 
-        ui.label("Sawtooth:");
+        self.chord_library_file(ui);
+
+        ui.separator();
+
+        let chord = &library.chords()[self.finder_chord];
+        let voicings = self.voicing_cache.get(&self.guitar, self.finder_root, chord);
+        let chord_name = chord.short_name(&root_name(self.finder_root));
+        match self.guitar.capo() {
+            Some(capo) => {
+                let shape_root = root_name(notes_sub(self.finder_root, capo % 12));
+                ui.label(format!(
+                    "{} voicings of {chord_name}, {} shapes with capo {capo}:",
+                    voicings.len(),
+                    chord.short_name(&shape_root)
+                ))
+            }
+            None => ui.label(format!("{} voicings of {chord_name}:", voicings.len())),
+        };
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                for (voicing, fingering) in voicings.iter() {
+                    let hover = format!(
+                        "Fingers {}, playability {:.0}%",
+                        fingering.label(voicing),
+                        fingering.score * 100.0
+                    );
+                    let dots = voicing
+                        .strings()
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(string, state)| {
+                            let StringState::Fretted(fret) = *state else {
+                                return None;
+                            };
+                            let note = self.guitar.fret_to_note(string, fret);
+                            let text = fingering.fingers[string].map_or("", |f| f.label());
+                            Some(Dot::new(
+                                string,
+                                fret,
+                                text,
+                                interval_color(notes_sub(note, self.finder_root)),
+                            ))
+                        })
+                        .collect();
+                    let clicked = ui
+                        .vertical(|ui| {
+                            let fretboard = Fretboard::new(&self.guitar)
+                                .orientation(Orientation::Vertical)
+                                .frets(diagram_frets(voicing))
+                                .string_spacing(DIAGRAM_STRING_SPACING)
+                                .fret_numbers(false)
+                                .voicing(voicing)
+                                .barres(&fingering.barres)
+                                .dots(dots)
+                                .show(ui);
+                            ui.label(voicing.to_string());
+                            // Naming the capo shape runs the identifier twice, only do it for the hovered voicing
+                            let response = fretboard.response.on_hover_ui(|ui| {
+                                ui.label(hover);
+                                if let Some(names) = self.capo_chord_names(&library, voicing) {
+                                    ui.label(names);
+                                }
+                            });
+                            response.clicked()
+                        })
+                        .inner;
+                    if clicked {
+                        self.frets_selected = voicing.clone();
+                        self.selected_tab = GuitarChordsTabs::ChordIdentifier;
+                    }
+                }
+            });
+        });
+    }
+
+    fn scale_explorer(&mut self, ui: &mut Ui) {
+        self.pick_guitar(ui);
+        ui.separator();
+
         ui.horizontal(|ui| {
-            for (note, freq) in &notes {
-                if ui.button("Play ".to_string() + note).clicked() {
-                    self.audio_streams.push(play_frequency_sawtooth(*freq));
+            ui.label("Root:");
+            egui::ComboBox::from_id_salt("scale_root")
+                .selected_text(root_name(self.scale_root))
+                .width(40.0)
+                .show_ui(ui, |ui| {
+                    for note in 0..12 {
+                        ui.selectable_value(&mut self.scale_root, note, root_name(note));
+                    }
+                });
+
+            ui.label("Scale:");
+            egui::ComboBox::from_id_salt("scale")
+                .selected_text(all_scales()[self.scale].name())
+                .height(400.0)
+                .show_ui(ui, |ui| {
+                    let mut family = "";
+                    for (i, scale) in all_scales().iter().enumerate() {
+                        if scale.family() != family {
+                            family = scale.family();
+                            ui.label(egui::RichText::new(family).strong());
+                        }
+                        ui.selectable_value(&mut self.scale, i, scale.name());
+                    }
+                });
+        });
+
+        let scale = &all_scales()[self.scale];
+        let spelled = scale.spell(root_spelling(self.scale_root));
+        ui.horizontal(|ui| {
+            ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+            for (note, degree) in spelled.iter().zip(scale.degree_names()) {
+                ui.label(format!("{degree}: {note}"));
+            }
+        });
+
+        self.diatonic_chords(ui, scale);
+
+        ui.separator();
+
+        let dots = (0..self.guitar.guitar_strings.len())
+            .flat_map(|string| (0..=self.guitar.fret_count).map(move |fret| (string, fret)))
+            .filter_map(|(string, fret)| {
+                let note = self.guitar.fret_to_note(string, fret);
+                let degree = scale.degree_of(self.scale_root, note)?;
+                let color = interval_color(notes_sub(note, self.scale_root));
+                Some(Dot::new(string, fret, spelled[degree].to_string(), color))
+            })
+            .collect();
+        egui::ScrollArea::both()
+            .id_salt("scale_fretboard")
+            .max_height(FRETBOARD_MAX_HEIGHT)
+            .show(ui, |ui| {
+                Fretboard::new(&self.guitar)
+                    .orientation(self.fretboard_orientation)
+                    .string_labels(true)
+                    .dots(dots)
+                    .show(ui);
+            });
+    }
+
+    fn diatonic_chords(&mut self, ui: &mut Ui, scale: &Scale) {
+        let library = self.chord_library.clone();
+        let chords = diatonic_chords(self.scale_root, scale, self.diatonic_sevenths, &library);
+        if chords.is_empty() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Diatonic chords:");
+            ui.selectable_value(&mut self.diatonic_sevenths, false, "Triads");
+            ui.selectable_value(&mut self.diatonic_sevenths, true, "Seventh chords");
+        });
+        ui.horizontal_wrapped(|ui| {
+            for diatonic_chord in chords {
+                let notes = diatonic_chord.notes.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+                let Some(chord) = diatonic_chord.chord else {
+                    ui.label(format!("{}: {}", diatonic_chord.numeral, notes.join(" ")));
+                    continue;
+                };
+                let text = format!(
+                    "{}: {} ({}, {})",
+                    diatonic_chord.numeral,
+                    chord.short_name(&diatonic_chord.notes[0].to_string()),
+                    notes.join(" "),
+                    diatonic_chord.function.name()
+                );
+                if ui.link(text).clicked() {
+                    self.finder_root = diatonic_chord.root;
+                    self.finder_chord = library.position(chord).unwrap();
+                    self.selected_tab = GuitarChordsTabs::ChordFinder;
+                }
+                ui.separator();
+            }
+        });
+    }
+
+    fn progression_editor(&mut self, ui: &mut Ui) {
+        self.pick_guitar(ui);
+        ui.separator();
+
+        let library = self.chord_library.clone();
+        self.progression = self.progression.min(self.progressions.len() - 1);
+
+        ui.horizontal(|ui| {
+            ui.label("Progression:");
+            let selected = self.progression;
+            egui::ComboBox::from_id_salt("progression")
+                .selected_text(&self.progressions[self.progression].name)
+                .show_ui(ui, |ui| {
+                    for (i, progression) in self.progressions.iter().enumerate() {
+                        ui.selectable_value(&mut self.progression, i, &progression.name);
+                    }
+                });
+            if ui.button("New").clicked() {
+                self.progressions.push(Progression::new("Untitled"));
+                self.progression = self.progressions.len() - 1;
+            }
+            if ui.button("Duplicate").clicked() {
+                let mut progression = self.progressions[self.progression].clone();
+                progression.name += " (copy)";
+                self.progressions.push(progression);
+                self.progression = self.progressions.len() - 1;
+            }
+            if ui
+                .add_enabled(self.progressions.len() > 1, egui::Button::new("Delete"))
+                .clicked()
+            {
+                self.progressions.remove(self.progression);
+                self.progression = self.progression.min(self.progressions.len() - 1);
+                self.progression_playback = None;
+            }
+            if self.progression != selected {
+                self.progression_playback = None;
+            }
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut self.progressions[self.progression].name);
+        });
+
+        let progression = &mut self.progressions[self.progression];
+        ui.horizontal(|ui| {
+            ui.label("Key:");
+            egui::ComboBox::from_id_salt("progression_key")
+                .selected_text(progression.key.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in [KeyMode::Major, KeyMode::Minor] {
+                        for tonic in 0..12 {
+                            let key = Key::new(tonic, mode);
+                            ui.selectable_value(&mut progression.key, key, key.to_string());
+                        }
+                    }
+                });
+            if ui.button("Detect").clicked() {
+                let chords = progression
+                    .chords
+                    .iter()
+                    .filter_map(|c| c.parse().ok())
+                    .map(|symbol| (symbol.root.note(), symbol.intervals))
+                    .collect::<Vec<_>>();
+                let chords = chords.iter().map(|(root, intervals)| (*root, intervals.as_slice()));
+                if let Some(estimate) = detect_key_from_chords(&chords.collect::<Vec<_>>()).first() {
+                    progression.key = estimate.key;
                 }
             }
+
+            ui.separator();
+            ui.label("Tempo:");
+            ui.add(
+                egui::DragValue::new(&mut progression.tempo)
+                    .range(20.0..=300.0)
+                    .suffix(" bpm"),
+            )
+            .on_hover_text("Quarter notes per minute");
+            ui.label("Time:");
+            ui.add(egui::DragValue::new(&mut progression.time_signature.beats_per_bar).range(1..=16));
+            ui.label("/");
+            egui::ComboBox::from_id_salt("beat_unit")
+                .selected_text(progression.time_signature.beat_unit.to_string())
+                .width(30.0)
+                .show_ui(ui, |ui| {
+                    for unit in [2, 4, 8, 16] {
+                        ui.selectable_value(&mut progression.time_signature.beat_unit, unit, unit.to_string());
+                    }
+                });
         });
 
-        ui.label("Karplus-Strong:");
         ui.horizontal(|ui| {
-            for (note, freq) in &notes {
-                if ui.button("Play ".to_string() + note).clicked() {
-                    self.audio_streams.push(playe_frequency_karplus_strong(*freq));
+            ui.label("Add chords:");
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.progression_input)
+                    .hint_text("Cm7 F7 | Bbmaj7 %")
+                    .desired_width(300.0),
+            );
+            let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Add").clicked() || submitted {
+                match progression.push_symbols(&self.progression_input) {
+                    Ok(()) => {
+                        self.progression_input.clear();
+                        self.progression_input_error = None;
+                    }
+                    Err(e) => self.progression_input_error = Some(e.to_string()),
+                }
+            }
+            if ui.button("Clear").clicked() {
+                progression.chords.clear();
+            }
+            if let Some(error) = &self.progression_input_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+
+        let key = progression.key;
+        let scale = all_scales().iter().find(|s| s.intervals() == key.scale()).unwrap();
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Diatonic chords:");
+            ui.selectable_value(&mut self.diatonic_sevenths, false, "Triads");
+            ui.selectable_value(&mut self.diatonic_sevenths, true, "Seventh chords");
+            for diatonic_chord in diatonic_chords(key.tonic, scale, self.diatonic_sevenths, &library) {
+                let Some(chord) = diatonic_chord.chord else {
+                    continue;
+                };
+                let symbol = chord.short_name(&diatonic_chord.notes[0].to_string());
+                if ui.button(format!("{} {symbol}", diatonic_chord.numeral)).clicked() {
+                    progression.chords.push(ProgressionChord::new(&symbol));
                 }
             }
         });
 
         ui.separator();
 
+        let resolved = progression
+            .chords
+            .iter()
+            .map(|c| {
+                let symbol = c.parse().ok()?;
+                Some((symbol.root.note(), symbol.chord(&library)?))
+            })
+            .collect::<Vec<_>>();
+        let mut analysis =
+            analyze_progression(key, &resolved.iter().flatten().copied().collect::<Vec<_>>()).into_iter();
+        let playing = self
+            .progression_playback
+            .as_ref()
+            .and_then(|p| p.chord)
+            .map(|(_, chord)| chord);
+
         ui.horizontal(|ui| {
-            if ui.button("Stop all").clicked() {
-                self.audio_streams.clear();
+            if self.progression_playback.is_some() {
+                if ui.button("Stop").clicked() {
+                    self.progression_playback = None;
+                }
+            } else if ui.button("Play").clicked() {
+                self.progression_playback = Some(ProgressionPlayback {
+                    started: Instant::now(),
+                    chord: None,
+                    streams: vec![],
+                });
             }
+            ui.checkbox(&mut self.progression_loop, "Loop");
+            ui.label(format!(
+                "{} bars, {:.1} s",
+                progression.total_bars(),
+                progression.duration()
+            ));
+        });
 
-            if ui.button("Pause all").clicked() {
-                for stream in self.audio_streams.iter_mut() {
-                    stream.pause().expect("pause failed");
+        ui.horizontal(|ui| {
+            let options = &mut self.voice_leading_options;
+            ui.label("Max fret span:");
+            ui.add(egui::DragValue::new(&mut options.max_fret_span).range(1..=MAX_FRET_SPAN));
+            ui.label("Frets:");
+            ui.add(egui::DragValue::new(&mut options.lowest_fret).range(0..=options.highest_fret));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut options.highest_fret).range(options.lowest_fret..=self.guitar.fret_count));
+            if ui.button("Optimize voice leading").clicked() {
+                let chords = resolved.iter().flatten().copied().collect::<Vec<_>>();
+                // Errors count only the resolved chords, report the position in the whole progression
+                let positions = (0..resolved.len())
+                    .filter(|&i| resolved[i].is_some())
+                    .collect::<Vec<_>>();
+                let result = optimize_voice_leading(&self.guitar, &chords, options).map_err(|error| match error {
+                    VoiceLeadingError::NoVoicing { chord } => VoiceLeadingError::NoVoicing {
+                        chord: positions[chord],
+                    },
+                });
+                match result {
+                    Ok(voice_leading) => {
+                        let mut voicings = voice_leading.voicings.into_iter();
+                        let mut symbols = vec![];
+                        for (chord, _) in progression
+                            .chords
+                            .iter_mut()
+                            .zip(&resolved)
+                            .filter(|(_, r)| r.is_some())
+                        {
+                            chord.frets = voicings.next().unwrap();
+                            symbols.push(chord.symbol.as_str());
+                        }
+                        self.voice_leading_report = voice_leading
+                            .transitions
+                            .iter()
+                            .enumerate()
+                            .map(|(i, cost)| format!("{} -> {}: {cost}", symbols[i], symbols[i + 1]))
+                            .collect();
+                        self.voice_leading_error = None;
+                    }
+                    Err(e) => self.voice_leading_error = Some(e.to_string()),
                 }
             }
+            if let Some(error) = &self.voice_leading_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
+        if !self.voice_leading_report.is_empty() {
+            ui.collapsing("Voice leading costs", |ui| {
+                for line in &self.voice_leading_report {
+                    ui.label(line);
+                }
+            });
+        }
 
-            if ui.button("Un-Pause all").clicked() {
-                for stream in self.audio_streams.iter_mut() {
-                    stream.play().expect("play failed");
+        let mut removed = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (i, chord) in progression.chords.iter_mut().enumerate() {
+                    let resolved = resolved[i];
+                    let numeral = resolved.and_then(|_| analysis.next());
+                    ui.group(|ui| {
+                        ui.vertical(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(&chord.symbol)
+                                        .strong()
+                                        .color(if playing == Some(i) {
+                                            ui.visuals().warn_fg_color
+                                        } else {
+                                            ui.visuals().text_color()
+                                        }),
+                                );
+                                if ui.small_button("X").clicked() {
+                                    removed = Some(i);
+                                }
+                            });
+                            match &numeral {
+                                Some(analysis) => ui.label(&analysis.numeral).on_hover_text(analysis.function.name()),
+                                None => ui.label("?").on_hover_text("not in the chord dictionary"),
+                            };
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut chord.bars).range(1..=16));
+                                ui.label("bars");
+                            });
+                            let Some((root, library_chord)) = resolved else {
+                                return;
+                            };
+                            let voicings = self.voicing_cache.get(&self.guitar, root, library_chord);
+                            if chord.frets.len() != self.guitar.guitar_strings.len() {
+                                chord.frets = voicings.first().map(|(v, _)| v.clone()).unwrap_or_default();
+                            }
+                            ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                            egui::ComboBox::from_id_salt(("progression_voicing", i))
+                                .selected_text(chord.frets.to_string())
+                                .height(300.0)
+                                .show_ui(ui, |ui| {
+                                    for (voicing, _) in voicings.iter() {
+                                        ui.selectable_value(&mut chord.frets, voicing.clone(), voicing.to_string());
+                                    }
+                                });
+                        });
+                    });
                 }
+            });
+        });
+        if let Some(i) = removed {
+            progression.chords.remove(i);
+        }
+    }
+
+    fn advance_progression_playback(&mut self, ctx: &egui::Context) {
+        let Some(playback) = &self.progression_playback else {
+            return;
+        };
+        let progression = &self.progressions[self.progression];
+        let mut elapsed = playback.started.elapsed().as_secs_f32();
+        let mut pass = 0;
+        if self.progression_loop && progression.duration() > 0.0 {
+            pass = (elapsed / progression.duration()).floor() as u32;
+            elapsed %= progression.duration();
+        }
+        let Some(chord) = progression.chord_at(elapsed) else {
+            self.progression_playback = None;
+            return;
+        };
+        if playback.chord != Some((pass, chord)) {
+            // Voicings picked before the tuning was edited no longer fit the strings
+            let frets = &progression.chords[chord].frets;
+            let streams = if frets.len() == self.guitar.guitar_strings.len() {
+                self.play_frets(frets)
+            } else {
+                vec![]
+            };
+            let playback = self.progression_playback.as_mut().unwrap();
+            playback.chord = Some((pass, chord));
+            playback.streams = streams;
+        }
+        ctx.request_repaint_after(Duration::from_millis(10));
+    }
+
+    fn chord_library_file(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Chord library file:");
+            ui.text_edit_singleline(&mut self.chord_library_path);
+            if ui.button("Load").clicked() {
+                self.load_chord_library();
+            }
+            if ui.button("Built-in only").clicked() {
+                self.chord_library = Rc::new(ChordLibrary::builtin());
+                self.chord_library_error = None;
+            }
+            if let Some(error) = &self.chord_library_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
             }
         });
-        // TODO
     }
 
-    fn chord_finder(&mut self, ui: &mut Ui) {
-        ui.label("TODO: Chord finder");
-        // TODO
+    fn load_chord_library(&mut self) {
+        let mut library = ChordLibrary::builtin();
+        match library.load_file(Path::new(&self.chord_library_path)) {
+            Ok(()) => {
+                self.chord_library = Rc::new(library);
+                self.chord_library_error = None;
+            }
+            Err(e) => self.chord_library_error = Some(e.to_string()),
+        }
+    }
+
+    fn pick_temperament(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Temperament:");
+            egui::ComboBox::from_id_salt("temperament")
+                .selected_text(self.intonation.temperament.name())
+                .show_ui(ui, |ui| {
+                    for temperament in TEMPERAMENTS {
+                        let selected = std::mem::discriminant(&self.intonation.temperament)
+                            == std::mem::discriminant(&temperament);
+                        if ui.selectable_label(selected, temperament.name()).clicked() && !selected {
+                            self.intonation.temperament = temperament;
+                        }
+                    }
+                });
+
+            ui.label("Tonic:");
+            egui::ComboBox::from_id_salt("temperament_tonic")
+                .selected_text(root_name(self.intonation.tonic))
+                .width(40.0)
+                .show_ui(ui, |ui| {
+                    for note in 0..12 {
+                        ui.selectable_value(&mut self.intonation.tonic, note, root_name(note));
+                    }
+                });
+        });
+
+        if let Temperament::Custom(offsets) = &mut self.intonation.temperament {
+            ui.horizontal(|ui| {
+                ui.label("Cents offsets:");
+                for (degree, offset) in offsets.iter_mut().enumerate() {
+                    let tonic = root_spelling(self.intonation.tonic);
+                    ui.label(spell_in_key(notes_add(tonic.note(), degree as Note), tonic).to_string());
+                    ui.add(egui::DragValue::new(offset).range(-100.0..=100.0).speed(0.1));
+                }
+            });
+        }
     }
 
     fn pick_guitar(&mut self, ui: &mut Ui) {
         ui.horizontal(|ui| {
-            let guitar_6_string_standard_button = ui.selectable_label(
-                self.guitar == Guitar::guitar_6_string_standard(),
-                "guitar 6 string standard",
-            );
-            if guitar_6_string_standard_button.clicked() && self.guitar != Guitar::guitar_6_string_standard() {
-                self.guitar = Guitar::guitar_6_string_standard();
-                self.frets_selected = vec![None; 6];
+            ui.label("Tuning:");
+            let preset = find_tuning(&self.guitar);
+            let selected_text = preset.map_or("custom".to_string(), |p| format!("{} ({})", p.name(), p.category()));
+            egui::ComboBox::from_id_salt("tuning")
+                .selected_text(selected_text)
+                .height(400.0)
+                .show_ui(ui, |ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.tuning_search).hint_text("search tunings"));
+                    let search = self.tuning_search.to_lowercase();
+                    let mut category = "";
+                    for tuning in all_tunings().iter().filter(|t| {
+                        [t.name(), t.category(), &t.description()]
+                            .iter()
+                            .any(|text| text.to_lowercase().contains(&search))
+                    }) {
+                        if tuning.category() != category {
+                            category = tuning.category();
+                            ui.label(egui::RichText::new(category).strong());
+                        }
+                        let selected = preset.is_some_and(|p| std::ptr::eq(p, tuning));
+                        let button = ui
+                            .selectable_label(selected, tuning.name())
+                            .on_hover_text(tuning.description());
+                        if button.clicked() && !selected {
+                            self.guitar = tuning.guitar();
+                            self.frets_selected = FretPattern::muted(self.guitar.guitar_strings.len());
+                        }
+                    }
+                });
+
+            ui.separator();
+
+            ui.label("Capo:");
+            let mut capo = self.guitar.capo().unwrap_or(0);
+            let capo_input = ui.add(egui::DragValue::new(&mut capo).range(0..=self.guitar.fret_count));
+            if capo_input.changed() {
+                self.guitar.set_capo(capo);
+                self.mute_frets_under_capo();
+            }
+            if self.guitar.has_capo() && self.guitar.capo().is_none() {
+                ui.label("(partial)");
             }
+        });
 
-            let bass_4_string_standard = ui.selectable_label(
-                self.guitar == Guitar::bass_4_string_standard(),
-                "bass 4 string standard",
-            );
-            if bass_4_string_standard.clicked() && self.guitar != Guitar::bass_4_string_standard() {
-                self.guitar = Guitar::bass_4_string_standard();
-                self.frets_selected = vec![None; 4];
+        ui.horizontal(|ui| {
+            ui.label("Frets:");
+            let fret_count = ui.add(egui::DragValue::new(&mut self.guitar.fret_count).range(12..=36));
+            if fret_count.changed() {
+                self.frets_selected.clamp(self.guitar.fret_count);
+            }
+
+            ui.separator();
+
+            let strings = &self.guitar.guitar_strings;
+            let mut treble = strings.first().map_or(GUITAR_SCALE_LENGTH, |s| s.scale_length);
+            let mut bass = strings.last().map_or(GUITAR_SCALE_LENGTH, |s| s.scale_length);
+            ui.label("Scale length:");
+            let treble_input = ui.add(egui::DragValue::new(&mut treble).range(300.0..=1000.0).suffix(" mm"));
+            ui.label("to");
+            let bass_input = ui.add(egui::DragValue::new(&mut bass).range(300.0..=1000.0).suffix(" mm"));
+            if treble_input.changed() || bass_input.changed() {
+                self.guitar.set_scale_lengths(treble, bass);
+            }
+            if self.guitar.is_multiscale() {
+                ui.label("Perpendicular fret:");
+                ui.add(egui::DragValue::new(&mut self.guitar.perpendicular_fret).range(0..=self.guitar.fret_count));
             }
 
-            let bass_5_string_standard_button = ui.selectable_label(
-                self.guitar == Guitar::bass_5_string_standard(),
-                "bass 5 string standard",
+            ui.separator();
+
+            ui.label("Fretboard:");
+            ui.selectable_value(&mut self.fretboard_orientation, Orientation::Horizontal, "horizontal");
+            ui.selectable_value(&mut self.fretboard_orientation, Orientation::Vertical, "vertical");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Instrument:");
+            let saved = self.instruments.iter().position(|i| i.guitar == self.guitar);
+            egui::ComboBox::from_id_salt("instrument")
+                .selected_text(saved.map_or("unsaved", |i| self.instruments[i].name.as_str()))
+                .show_ui(ui, |ui| {
+                    for instrument in &self.instruments {
+                        if ui
+                            .selectable_label(instrument.guitar == self.guitar, &instrument.name)
+                            .clicked()
+                            && instrument.guitar != self.guitar
+                        {
+                            self.guitar = instrument.guitar.clone();
+                            self.frets_selected = FretPattern::muted(self.guitar.guitar_strings.len());
+                            self.instrument_name = instrument.name.clone();
+                        }
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.instrument_name)
+                    .hint_text("name")
+                    .desired_width(120.0),
             );
-            if bass_5_string_standard_button.clicked() && self.guitar != Guitar::bass_5_string_standard() {
-                self.guitar = Guitar::bass_5_string_standard();
-                self.frets_selected = vec![None; 5];
+            let name = self.instrument_name.trim().to_string();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save")).clicked() {
+                save_instrument(&mut self.instruments, Instrument::new(&name, self.guitar.clone()));
+            }
+            if let Some(i) = saved
+                && ui.button("Delete").clicked()
+            {
+                self.instruments.remove(i);
+            }
+
+            ui.separator();
+
+            ui.label("Instrument file:");
+            ui.text_edit_singleline(&mut self.instruments_path);
+            if ui.button("Import").clicked() {
+                match import_instruments(Path::new(&self.instruments_path)) {
+                    Ok(instruments) => {
+                        for instrument in instruments {
+                            save_instrument(&mut self.instruments, instrument);
+                        }
+                        self.instruments_error = None;
+                    }
+                    Err(e) => self.instruments_error = Some(e.to_string()),
+                }
+            }
+            if ui.button("Export").clicked() {
+                self.instruments_error = export_instruments(Path::new(&self.instruments_path), &self.instruments)
+                    .err()
+                    .map(|e| e.to_string());
+            }
+            if let Some(error) = &self.instruments_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
             }
         });
     }
 }
 
-#[derive(PartialEq)]
+const MAX_CHORD_MATCHES: usize = 12;
+const FRETBOARD_MAX_HEIGHT: f32 = 420.0;
+const DIAGRAM_STRING_SPACING: f32 = 14.0;
+// Wide enough for a column of string controls
+const VERTICAL_STRING_SPACING: f32 = 64.0;
+const MAX_LIKELY_KEYS: usize = 3;
+const STATE_KEY: &str = "state";
+// Bump when a saved field changes meaning, and add the upgrade step to `migrate_state`
+const STATE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct SavedState {
+    version: u32,
+    guitar: Guitar,
+    frets_selected: FretPattern,
+    selected_tab: GuitarChordsTabs,
+    intonation: Intonation,
+    playback_octave_from: i8,
+    playback_octave_to: i8,
+    volume: f32,
+    match_options: MatchOptions,
+    identifier_key: Option<Key>,
+    finder_root: Note,
+    finder_chord: usize,
+    scale_root: Note,
+    scale: usize,
+    diatonic_sevenths: bool,
+    chord_library_path: String,
+    instruments: Vec<Instrument>,
+    progressions: Vec<Progression>,
+    progression: usize,
+    progression_loop: bool,
+    voice_leading_options: VoiceLeadingOptions,
+    fretboard_orientation: Orientation,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        GuitarChordsApp::default().saved_state()
+    }
+}
+
+#[derive(Deserialize)]
+struct StateVersion {
+    version: u32,
+}
+
+fn load_state(storage: &dyn eframe::Storage) -> Option<SavedState> {
+    match eframe::get_value::<StateVersion>(storage, STATE_KEY) {
+        None => legacy_state(storage).map(migrate_state),
+        // Written by a newer release, better to start fresh than to misread it
+        Some(StateVersion { version }) if version > STATE_VERSION => None,
+        Some(_) => eframe::get_value(storage, STATE_KEY).map(migrate_state),
+    }
+}
+
+// Version 0 kept the progressions, instruments, guitar, frets and tab under keys of their own
+fn legacy_state(storage: &dyn eframe::Storage) -> Option<SavedState> {
+    let mut state = SavedState {
+        version: 0,
+        ..Default::default()
+    };
+    let mut found = false;
+    if let Some(progressions) = eframe::get_value(storage, "progressions") {
+        state.progressions = progressions;
+        found = true;
+    }
+    if let Some(instruments) = eframe::get_value(storage, "instruments") {
+        state.instruments = instruments;
+        found = true;
+    }
+    if let Some(guitar) = eframe::get_value(storage, "guitar") {
+        state.guitar = guitar;
+        state.frets_selected = eframe::get_value(storage, "frets_selected").unwrap_or_default();
+        found = true;
+    }
+    if let Some(tab) = eframe::get_value(storage, "selected_tab") {
+        state.selected_tab = tab;
+        found = true;
+    }
+    found.then_some(state)
+}
+
+fn migrate_state(mut state: SavedState) -> SavedState {
+    if state.version < 1 {
+        // Nothing changed shape, the fields only moved under one key
+        state.version = 1;
+    }
+    if state.version < 2 {
+        // Fret patterns are saved in "x32010" notation now, `FretPattern` still reads the old lists of frets
+        state.version = 2;
+    }
+    state
+}
+
+type PlayPitch = fn(Pitch, &Intonation, f32) -> cpal::Stream;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum GuitarChordsTabs {
     ChordIdentifier,
     AudioPlayback,
     ChordFinder,
+    ScaleExplorer,
+    ProgressionEditor,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::Storage;
+
+    #[derive(Default)]
+    struct MemoryStorage(HashMap<String, String>);
+
+    impl eframe::Storage for MemoryStorage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_string(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn round_trips_the_current_state() {
+        let mut app = GuitarChordsApp {
+            guitar: Guitar::bass_4_string_standard(),
+            frets_selected: "x320".parse().unwrap(),
+            selected_tab: GuitarChordsTabs::ScaleExplorer,
+            fretboard_orientation: Orientation::Vertical,
+            volume: 0.25,
+            identifier_key: Some(Key::minor(E)),
+            finder_root: G,
+            scale: 2,
+            progression_loop: true,
+            ..Default::default()
+        };
+        let mut storage = MemoryStorage::default();
+        eframe::App::save(&mut app, &mut storage);
+        assert!(storage.0.contains_key(STATE_KEY));
+
+        let state = load_state(&storage).unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.guitar, app.guitar);
+        assert_eq!(state.frets_selected, app.frets_selected);
+        assert!(state.selected_tab == GuitarChordsTabs::ScaleExplorer);
+        assert_eq!(state.fretboard_orientation, Orientation::Vertical);
+
+        let mut restored = GuitarChordsApp::default();
+        restored.apply_state(state);
+        assert_eq!(restored.volume, 0.25);
+        assert_eq!(restored.identifier_key, Some(Key::minor(E)));
+        assert_eq!(restored.finder_root, G);
+        assert_eq!(restored.scale, 2);
+        assert!(restored.progression_loop);
+    }
+
+    #[test]
+    fn raising_the_capo_mutes_covered_frets() {
+        let mut app = GuitarChordsApp {
+            frets_selected: "x32010".parse().unwrap(),
+            ..Default::default()
+        };
+        app.guitar.set_capo(2);
+        app.mute_frets_under_capo();
+        assert_eq!(app.frets_selected.to_string(), "x3x0x0");
+    }
+
+    #[test]
+    fn migrates_the_legacy_keys() {
+        let mut storage = MemoryStorage::default();
+        assert!(load_state(&storage).is_none());
+
+        let guitar = Guitar::bass_5_string_standard();
+        eframe::set_value(&mut storage, "guitar", &guitar);
+        eframe::set_value(&mut storage, "frets_selected", &vec![Some(0), Some(1), None]);
+        eframe::set_value(&mut storage, "selected_tab", &GuitarChordsTabs::ChordFinder);
+        let state = load_state(&storage).unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.guitar, guitar);
+        assert_eq!(state.frets_selected.to_string(), "x10");
+        assert!(state.selected_tab == GuitarChordsTabs::ChordFinder);
+    }
+
+    #[test]
+    fn reads_version_1_fret_lists() {
+        let mut storage = MemoryStorage::default();
+        storage.set_string(
+            STATE_KEY,
+            "(version: 1, frets_selected: [Some(3), None, Some(12)], selected_tab: ProgressionEditor)".to_string(),
+        );
+        let state = load_state(&storage).unwrap();
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.frets_selected.to_string(), "12-x-3");
+        assert!(state.selected_tab == GuitarChordsTabs::ProgressionEditor);
+        assert_eq!(state.guitar, SavedState::default().guitar);
+    }
+
+    #[test]
+    fn ignores_state_from_a_newer_version() {
+        let mut storage = MemoryStorage::default();
+        storage.set_string(
+            STATE_KEY,
+            format!("(version: {}, some_new_field: 1)", STATE_VERSION + 1),
+        );
+        eframe::set_value(&mut storage, "guitar", &Guitar::bass_4_string_standard());
+        assert!(load_state(&storage).is_none());
+    }
 }
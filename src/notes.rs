@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 pub type Note = u8;
 
 pub const C: Note = 0;
@@ -64,3 +66,307 @@ pub fn note_name(note: Note) -> String {
         _ => unreachable!(),
     }
 }
+
+const LETTER_NAMES: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+const LETTER_NOTES: [Note; 7] = [C, D, E, F, G, A, B];
+// Double sharps and flats are as far as note names go
+const MAX_ACCIDENTALS: i8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpelledNote {
+    pub letter: u8,
+    pub accidental: i8,
+}
+
+impl SpelledNote {
+    pub fn new(letter: u8, accidental: i8) -> Self {
+        Self {
+            letter: letter % 7,
+            accidental,
+        }
+    }
+
+    pub fn natural(note: Note) -> Option<Self> {
+        LETTER_NOTES
+            .iter()
+            .position(|&n| n == note)
+            .map(|letter| Self::new(letter as u8, 0))
+    }
+
+    pub fn with_sharp(note: Note) -> Self {
+        Self::natural(note).unwrap_or_else(|| Self::natural(notes_sub(note, 1)).unwrap().with_accidental(1))
+    }
+
+    pub fn with_flat(note: Note) -> Self {
+        Self::natural(note).unwrap_or_else(|| Self::natural(notes_add(note, 1)).unwrap().with_accidental(-1))
+    }
+
+    pub fn note(self) -> Note {
+        (LETTER_NOTES[self.letter as usize] as i16 + self.accidental as i16).rem_euclid(12) as Note
+    }
+
+    pub fn spell_interval(self, semitones: u8, degree: u8) -> Self {
+        let letter = (self.letter + degree.saturating_sub(1)) % 7;
+        let target = notes_add(self.note(), semitones % 12);
+        let natural = LETTER_NOTES[letter as usize];
+        let accidental = (notes_sub(target, natural) as i8 + 6).rem_euclid(12) - 6;
+        Self::new(letter, accidental)
+    }
+
+    pub fn parse_prefix(s: &str) -> Option<(Self, &str)> {
+        let mut chars = s.char_indices();
+        let (_, first) = chars.next()?;
+        let letter = LETTER_NAMES.iter().position(|&l| l == first.to_ascii_uppercase())?;
+        let mut accidental: i8 = 0;
+        let mut end = first.len_utf8();
+        for (i, c) in chars {
+            accidental += match c {
+                '#' | '♯' => 1,
+                'b' | '♭' => -1,
+                _ => break,
+            };
+            if accidental.abs() > MAX_ACCIDENTALS {
+                return None;
+            }
+            end = i + c.len_utf8();
+        }
+        Some((Self::new(letter as u8, accidental), &s[end..]))
+    }
+
+    fn with_accidental(self, accidental: i8) -> Self {
+        Self::new(self.letter, self.accidental + accidental)
+    }
+}
+
+impl std::str::FromStr for SpelledNote {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match SpelledNote::parse_prefix(s.trim()) {
+            Some((note, "")) => Ok(note),
+            _ => Err(format!("invalid note name '{s}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for SpelledNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let accidentals = if self.accidental >= 0 { "#" } else { "b" }.repeat(self.accidental.unsigned_abs() as usize);
+        write!(f, "{}{}", LETTER_NAMES[self.letter as usize], accidentals)
+    }
+}
+
+// Db, Eb, Ab and Bb are far more common as roots and keys than their sharp counterparts, F# wins over Gb
+pub fn root_spelling(note: Note) -> SpelledNote {
+    match note {
+        FS => SpelledNote::with_sharp(note),
+        _ => SpelledNote::with_flat(note),
+    }
+}
+
+pub fn root_name(note: Note) -> String {
+    root_spelling(note).to_string()
+}
+
+// Degrees of the chromatic notes relative to a tonic: b2, b3, #4, b6 and b7 for the non-diatonic ones
+const KEY_DEGREES: [u8; 12] = [1, 2, 2, 3, 3, 4, 4, 5, 6, 6, 7, 7];
+
+pub fn spell_in_key(note: Note, tonic: SpelledNote) -> SpelledNote {
+    let semitones = notes_sub(note, tonic.note());
+    tonic.spell_interval(semitones, KEY_DEGREES[semitones as usize])
+}
+
+pub fn spelled_button_label(note: SpelledNote) -> String {
+    format!("{:>2}\n", note.to_string())
+}
+
+pub fn note_from_name(name: &str) -> Option<Note> {
+    name.parse::<SpelledNote>().ok().map(|n| n.note())
+}
+
+// Stored as its name, "E2", so saved tunings stay readable
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Pitch(pub u8);
+
+impl Pitch {
+    // Clamped to the MIDI range, C-1 to G9
+    pub fn new(note: Note, octave: i8) -> Self {
+        Self(((octave as i16 + 1) * 12 + note as i16).clamp(0, 127) as u8)
+    }
+
+    pub fn note(self) -> Note {
+        self.0 % 12
+    }
+
+    pub fn octave(self) -> i8 {
+        (self.0 / 12) as i8 - 1
+    }
+
+    pub fn transpose(self, semitones: i16) -> Self {
+        Self((self.0 as i16 + semitones).clamp(0, 127) as u8)
+    }
+
+    pub fn semitones_to(self, other: Pitch) -> i16 {
+        other.0 as i16 - self.0 as i16
+    }
+
+    pub fn frequency(self, a4_frequency: f32) -> f32 {
+        a4_frequency * 2f32.powf(A4.semitones_to(self) as f32 / 12.0)
+    }
+}
+
+impl From<Pitch> for Note {
+    fn from(pitch: Pitch) -> Self {
+        pitch.note()
+    }
+}
+
+impl std::ops::Add<u8> for Pitch {
+    type Output = Pitch;
+
+    fn add(self, semitones: u8) -> Pitch {
+        self.transpose(semitones as i16)
+    }
+}
+
+impl std::ops::Sub<u8> for Pitch {
+    type Output = Pitch;
+
+    fn sub(self, semitones: u8) -> Pitch {
+        self.transpose(-(semitones as i16))
+    }
+}
+
+impl std::fmt::Display for Pitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", note_name(self.note()), self.octave())
+    }
+}
+
+impl std::str::FromStr for Pitch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let octave_start = s
+            .find(|c: char| c.is_ascii_digit() || c == '-')
+            .ok_or_else(|| format!("missing octave in pitch '{s}'"))?;
+        let (name, octave) = s.split_at(octave_start);
+        let name = name
+            .parse::<SpelledNote>()
+            .map_err(|_| format!("invalid note name '{name}' in pitch '{s}'"))?;
+        let octave = octave
+            .parse::<i8>()
+            .ok()
+            .filter(|o| (-1..=9).contains(o))
+            .ok_or_else(|| format!("invalid octave '{octave}' in pitch '{s}'"))?;
+        // Not `SpelledNote::note`, B#3 is C4 rather than C3
+        let midi = (octave as i16 + 1) * 12 + LETTER_NOTES[name.letter as usize] as i16 + name.accidental as i16;
+        if !(0..=127).contains(&midi) {
+            return Err(format!("pitch '{s}' is out of range"));
+        }
+        Ok(Pitch(midi as u8))
+    }
+}
+
+impl From<Pitch> for String {
+    fn from(pitch: Pitch) -> Self {
+        pitch.to_string()
+    }
+}
+
+impl TryFrom<String> for Pitch {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+pub const A4: Pitch = Pitch(69);
+pub const A4_REFERENCES: [(&str, f32); 4] = [
+    ("Standard (440 Hz)", 440.0),
+    ("Verdi (432 Hz)", 432.0),
+    ("Orchestral (442 Hz)", 442.0),
+    ("Baroque (415 Hz)", 415.0),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spelled(name: &str) -> SpelledNote {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn spells_intervals_by_letter() {
+        assert_eq!(spelled("Db").spell_interval(4, 3), spelled("F"));
+        assert_eq!(spelled("G#").spell_interval(7, 5), spelled("D#"));
+        assert_eq!(spelled("F#").spell_interval(4, 3), spelled("A#"));
+        assert_eq!(spelled("C").spell_interval(6, 5), spelled("Gb"));
+        assert_eq!(spelled("C").spell_interval(8, 5), spelled("G#"));
+        assert_eq!(spelled("Bb").spell_interval(11, 7), spelled("A"));
+        assert_eq!(spelled("Fb").note(), E);
+        assert_eq!(spelled("B##").to_string(), "B##");
+    }
+
+    #[test]
+    fn spells_notes_in_key() {
+        let names = |tonic, notes: &[Note]| {
+            notes
+                .iter()
+                .map(|&n| spell_in_key(n, spelled(tonic)).to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("E", &[GS, CS, DS]), ["G#", "C#", "D#"]);
+        assert_eq!(names("Db", &[F, AS, C]), ["F", "Bb", "C"]);
+        assert_eq!(names("C", &[CS, DS, FS, GS, AS]), ["Db", "Eb", "F#", "Ab", "Bb"]);
+        assert_eq!(root_name(GS), "Ab");
+        assert_eq!(root_name(FS), "F#");
+    }
+
+    #[test]
+    fn parses_and_formats_pitches() {
+        for name in ["C#4", "C-1", "G9", "E2", "A#0"] {
+            assert_eq!(name.parse::<Pitch>().unwrap().to_string(), name);
+        }
+        assert_eq!("C-1".parse(), Ok(Pitch(0)));
+        assert_eq!("G9".parse(), Ok(Pitch(127)));
+        assert_eq!("Db4".parse(), Ok(Pitch::new(CS, 4)));
+        assert_eq!(" B#3 ".parse(), Ok(Pitch::new(C, 4)));
+        assert!("G#9".parse::<Pitch>().is_err());
+        assert!("Cb-1".parse::<Pitch>().is_err());
+        assert!("C10".parse::<Pitch>().is_err());
+        assert!("H2".parse::<Pitch>().is_err());
+        assert!("E".parse::<Pitch>().is_err());
+        assert_eq!("Ebb3".parse(), Ok(Pitch::new(D, 3)));
+        assert!("C###4".parse::<Pitch>().is_err());
+        assert!(format!("C{}4", "#".repeat(200)).parse::<Pitch>().is_err());
+    }
+
+    #[test]
+    fn pitch_arithmetic_stays_in_range() {
+        let e2 = Pitch::new(E, 2);
+        assert_eq!((e2.note(), e2.octave()), (E, 2));
+        assert_eq!(e2 + 5, Pitch::new(A, 2));
+        assert_eq!(e2 - 5, Pitch::new(B, 1));
+        assert_eq!(e2.semitones_to(Pitch::new(E, 4)), 24);
+        assert_eq!(Pitch::new(E, 4).semitones_to(e2), -24);
+        assert_eq!(e2.transpose(200), Pitch(127));
+        assert_eq!(e2 - 100, Pitch(0));
+        assert_eq!(Pitch::new(G, 10), Pitch(127));
+        assert_eq!(Pitch::new(C, -2), Pitch(0));
+    }
+
+    #[test]
+    fn equal_tempered_frequencies() {
+        assert_eq!(A4.frequency(440.0), 440.0);
+        assert_eq!(A4.frequency(432.0), 432.0);
+        assert_eq!((A4 + 12).frequency(440.0), 880.0);
+        assert_eq!((A4 - 24).frequency(440.0), 110.0);
+        assert!((Pitch::new(C, 4).frequency(440.0) - 261.63).abs() < 0.01);
+        assert!((Pitch::new(E, 2).frequency(440.0) - 82.41).abs() < 0.01);
+    }
+}
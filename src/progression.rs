@@ -0,0 +1,167 @@
+use crate::chord_symbols::{ChordSymbol, ChordSymbolError, parse_chord_symbol};
+use crate::fret_pattern::FretPattern;
+use crate::keys::Key;
+use crate::notes::C;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProgressionChord {
+    pub symbol: String,
+    pub bars: u8,
+    // Empty until a voicing is picked, then one entry per guitar string
+    pub frets: FretPattern,
+}
+
+impl ProgressionChord {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            bars: 1,
+            frets: FretPattern::default(),
+        }
+    }
+
+    pub fn parse(&self) -> Result<ChordSymbol, ChordSymbolError> {
+        parse_chord_symbol(&self.symbol)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeSignature {
+    pub beats_per_bar: u8,
+    pub beat_unit: u8,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self {
+            beats_per_bar: 4,
+            beat_unit: 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Progression {
+    pub name: String,
+    pub key: Key,
+    // Quarter notes per minute, whatever the beat unit of the time signature
+    pub tempo: f32,
+    pub time_signature: TimeSignature,
+    pub chords: Vec<ProgressionChord>,
+}
+
+impl Default for Progression {
+    fn default() -> Self {
+        Self {
+            name: "Untitled".to_string(),
+            key: Key::major(C),
+            tempo: 100.0,
+            time_signature: TimeSignature::default(),
+            chords: vec![],
+        }
+    }
+}
+
+impl Progression {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn seconds_per_bar(&self) -> f32 {
+        let quarters_per_bar = self.time_signature.beats_per_bar as f32 * 4.0 / self.time_signature.beat_unit as f32;
+        quarters_per_bar * 60.0 / self.tempo
+    }
+
+    pub fn total_bars(&self) -> u32 {
+        self.chords.iter().map(|c| c.bars as u32).sum()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.total_bars() as f32 * self.seconds_per_bar()
+    }
+
+    pub fn chord_at(&self, seconds: f32) -> Option<usize> {
+        if seconds < 0.0 {
+            return None;
+        }
+        let bar = (seconds / self.seconds_per_bar()) as u32;
+        let mut end = 0;
+        self.chords.iter().position(|chord| {
+            end += chord.bars as u32;
+            bar < end
+        })
+    }
+
+    pub fn push_symbols(&mut self, text: &str) -> Result<(), ChordSymbolError> {
+        let chords = parse_progression(text)?;
+        self.chords.extend(chords);
+        Ok(())
+    }
+}
+
+// Chord symbols separated by whitespace or "|" bar lines, one bar each. A "%" repeats the previous chord for another bar
+pub fn parse_progression(text: &str) -> Result<Vec<ProgressionChord>, ChordSymbolError> {
+    let mut chords: Vec<ProgressionChord> = vec![];
+    for symbol in text
+        .split(|c: char| c.is_whitespace() || c == '|')
+        .filter(|s| !s.is_empty())
+    {
+        match chords.last_mut() {
+            Some(last) if symbol == "%" => last.bars = last.bars.saturating_add(1),
+            _ => {
+                parse_chord_symbol(symbol)?;
+                chords.push(ProgressionChord::new(symbol));
+            }
+        }
+    }
+    Ok(chords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_times_a_progression() {
+        let mut progression = Progression::new("ii-V-I");
+        progression.push_symbols("| Cm7 | F7 | Bbmaj7 | % |").unwrap();
+        assert_eq!(
+            progression
+                .chords
+                .iter()
+                .map(|c| (c.symbol.as_str(), c.bars))
+                .collect::<Vec<_>>(),
+            [("Cm7", 1), ("F7", 1), ("Bbmaj7", 2)]
+        );
+        assert_eq!(progression.duration(), 4.0 * 2.4);
+        assert_eq!(progression.chord_at(0.0), Some(0));
+        assert_eq!(progression.chord_at(3.0), Some(1));
+        assert_eq!(progression.chord_at(8.0), Some(2));
+        assert_eq!(progression.chord_at(10.0), None);
+
+        assert!(progression.push_symbols("C H7").is_err());
+        assert_eq!(progression.chords.len(), 3);
+    }
+
+    #[test]
+    fn bars_last_as_long_as_their_beat_unit() {
+        let mut progression = Progression::new("Waltz");
+        progression.tempo = 120.0;
+        progression.time_signature = TimeSignature {
+            beats_per_bar: 6,
+            beat_unit: 8,
+        };
+        assert_eq!(progression.seconds_per_bar(), 1.5);
+        progression.time_signature.beat_unit = 4;
+        assert_eq!(progression.seconds_per_bar(), 3.0);
+        progression.time_signature = TimeSignature {
+            beats_per_bar: 2,
+            beat_unit: 2,
+        };
+        assert_eq!(progression.seconds_per_bar(), 2.0);
+    }
+}
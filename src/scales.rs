@@ -0,0 +1,159 @@
+use crate::notes::{Note, SpelledNote, notes_add};
+use std::sync::LazyLock;
+
+const MAJOR_SCALE: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+pub struct Scale {
+    name: String,
+    family: String,
+    intervals: Vec<u8>,
+    degrees: Vec<(u8, String)>,
+}
+
+impl Scale {
+    pub fn new(name: &str, family: &str, degree_names: &str) -> Self {
+        let degrees = degree_names
+            .split_whitespace()
+            .map(|name| (degree_number(name), name.to_string()))
+            .collect::<Vec<_>>();
+        let intervals = degrees.iter().map(|(_, name)| degree_interval(name)).collect();
+        Self {
+            name: name.to_string(),
+            family: family.to_string(),
+            intervals,
+            degrees,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    pub fn intervals(&self) -> &[u8] {
+        &self.intervals
+    }
+
+    pub fn degree_names(&self) -> Vec<&str> {
+        self.degrees.iter().map(|(_, name)| name.as_str()).collect()
+    }
+
+    pub fn notes(&self, root: Note) -> Vec<Note> {
+        self.intervals.iter().map(|&i| notes_add(root, i)).collect()
+    }
+
+    pub fn contains(&self, root: Note, note: Note) -> bool {
+        self.notes(root).contains(&note)
+    }
+
+    pub fn degree_of(&self, root: Note, note: Note) -> Option<usize> {
+        self.notes(root).iter().position(|&n| n == note)
+    }
+
+    pub fn spell(&self, root: SpelledNote) -> Vec<SpelledNote> {
+        self.intervals
+            .iter()
+            .zip(&self.degrees)
+            .map(|(&interval, &(degree, _))| root.spell_interval(interval, degree))
+            .collect()
+    }
+}
+
+fn degree_number(name: &str) -> u8 {
+    name.trim_start_matches(['b', '#']).parse().unwrap()
+}
+
+fn degree_interval(name: &str) -> u8 {
+    let degree = degree_number(name);
+    let flats = name.chars().take_while(|&c| c == 'b').count() as i8;
+    let sharps = name.chars().take_while(|&c| c == '#').count() as i8;
+    (MAJOR_SCALE[(degree - 1) as usize % 7] as i8 + sharps - flats).rem_euclid(12) as u8
+}
+
+pub fn all_scales() -> &'static [Scale] {
+    static ALL_SCALES: LazyLock<Vec<Scale>> = LazyLock::new(|| {
+        vec![
+            // Major modes
+            Scale::new("Major (Ionian)", "Major modes", "1 2 3 4 5 6 7"),
+            Scale::new("Dorian", "Major modes", "1 2 b3 4 5 6 b7"),
+            Scale::new("Phrygian", "Major modes", "1 b2 b3 4 5 b6 b7"),
+            Scale::new("Lydian", "Major modes", "1 2 3 #4 5 6 7"),
+            Scale::new("Mixolydian", "Major modes", "1 2 3 4 5 6 b7"),
+            Scale::new("Natural Minor (Aeolian)", "Major modes", "1 2 b3 4 5 b6 b7"),
+            Scale::new("Locrian", "Major modes", "1 b2 b3 4 b5 b6 b7"),
+            // Harmonic minor modes
+            Scale::new("Harmonic Minor", "Harmonic minor modes", "1 2 b3 4 5 b6 7"),
+            Scale::new("Locrian #6", "Harmonic minor modes", "1 b2 b3 4 b5 6 b7"),
+            Scale::new("Ionian #5", "Harmonic minor modes", "1 2 3 4 #5 6 7"),
+            Scale::new("Dorian #4", "Harmonic minor modes", "1 2 b3 #4 5 6 b7"),
+            Scale::new("Phrygian Dominant", "Harmonic minor modes", "1 b2 3 4 5 b6 b7"),
+            Scale::new("Lydian #2", "Harmonic minor modes", "1 #2 3 #4 5 6 7"),
+            Scale::new("Super Locrian bb7", "Harmonic minor modes", "1 b2 b3 b4 b5 b6 bb7"),
+            // Melodic minor modes
+            Scale::new("Melodic Minor", "Melodic minor modes", "1 2 b3 4 5 6 7"),
+            Scale::new("Dorian b2", "Melodic minor modes", "1 b2 b3 4 5 6 b7"),
+            Scale::new("Lydian Augmented", "Melodic minor modes", "1 2 3 #4 #5 6 7"),
+            Scale::new("Lydian Dominant", "Melodic minor modes", "1 2 3 #4 5 6 b7"),
+            Scale::new("Mixolydian b6", "Melodic minor modes", "1 2 3 4 5 b6 b7"),
+            Scale::new("Locrian #2", "Melodic minor modes", "1 2 b3 4 b5 b6 b7"),
+            Scale::new("Altered", "Melodic minor modes", "1 b2 b3 b4 b5 b6 b7"),
+            // Pentatonics and blues
+            Scale::new("Major Pentatonic", "Pentatonic and blues", "1 2 3 5 6"),
+            Scale::new("Minor Pentatonic", "Pentatonic and blues", "1 b3 4 5 b7"),
+            Scale::new("Blues", "Pentatonic and blues", "1 b3 4 b5 5 b7"),
+            Scale::new("Major Blues", "Pentatonic and blues", "1 2 b3 3 5 6"),
+            // Symmetric
+            Scale::new("Whole Tone", "Symmetric", "1 2 3 #4 #5 b7"),
+            Scale::new("Diminished (Half-Whole)", "Symmetric", "1 b2 #2 3 #4 5 6 b7"),
+            Scale::new("Diminished (Whole-Half)", "Symmetric", "1 2 b3 4 b5 #5 6 7"),
+            // Bebop
+            Scale::new("Bebop Dominant", "Bebop", "1 2 3 4 5 6 b7 7"),
+            Scale::new("Bebop Major", "Bebop", "1 2 3 4 5 #5 6 7"),
+            Scale::new("Bebop Dorian", "Bebop", "1 2 b3 3 4 5 6 b7"),
+            // World
+            Scale::new("Hungarian Minor", "World", "1 2 b3 #4 5 b6 7"),
+            Scale::new("Double Harmonic (Byzantine)", "World", "1 b2 3 4 5 b6 7"),
+            Scale::new("Neapolitan Minor", "World", "1 b2 b3 4 5 b6 7"),
+            Scale::new("Neapolitan Major", "World", "1 b2 b3 4 5 6 7"),
+            Scale::new("Persian", "World", "1 b2 3 4 b5 b6 7"),
+            Scale::new("Enigmatic", "World", "1 b2 3 #4 #5 #6 7"),
+            Scale::new("Spanish 8-Tone", "World", "1 b2 #2 3 4 b5 b6 b7"),
+            Scale::new("Hirajoshi", "World", "1 2 b3 5 b6"),
+            Scale::new("In Sen", "World", "1 b2 4 5 b7"),
+            Scale::new("Iwato", "World", "1 b2 4 b5 b7"),
+            Scale::new("Kumoi", "World", "1 2 b3 5 6"),
+            Scale::new("Egyptian", "World", "1 2 4 5 b7"),
+        ]
+    });
+    &ALL_SCALES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_are_ascending_from_the_root() {
+        for scale in all_scales() {
+            assert_eq!(scale.intervals()[0], 0, "{}", scale.name());
+            assert!(scale.intervals().windows(2).all(|w| w[0] < w[1]), "{}", scale.name());
+        }
+    }
+
+    #[test]
+    fn major_modes_are_rotations_of_the_major_scale() {
+        let modes = all_scales()
+            .iter()
+            .filter(|s| s.family() == "Major modes")
+            .collect::<Vec<_>>();
+        for (i, mode) in modes.iter().enumerate() {
+            let rotated = (0..7)
+                .map(|d| (MAJOR_SCALE[(i + d) % 7] + 12 - MAJOR_SCALE[i]) % 12)
+                .collect::<Vec<_>>();
+            assert_eq!(mode.intervals(), rotated, "{}", mode.name());
+        }
+    }
+}
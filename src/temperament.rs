@@ -0,0 +1,187 @@
+use crate::notes::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Temperament {
+    Equal,
+    Just,
+    Pythagorean,
+    QuarterCommaMeantone,
+    WerckmeisterIII,
+    Custom([f32; 12]),
+}
+
+pub const TEMPERAMENTS: [Temperament; 6] = [
+    Temperament::Equal,
+    Temperament::Just,
+    Temperament::Pythagorean,
+    Temperament::QuarterCommaMeantone,
+    Temperament::WerckmeisterIII,
+    Temperament::Custom([0.0; 12]),
+];
+
+const JUST_RATIOS: [(u32, u32); 12] = [
+    (1, 1),
+    (16, 15),
+    (9, 8),
+    (6, 5),
+    (5, 4),
+    (4, 3),
+    (45, 32),
+    (3, 2),
+    (8, 5),
+    (5, 3),
+    (9, 5),
+    (15, 8),
+];
+
+const PYTHAGOREAN_RATIOS: [(u32, u32); 12] = [
+    (1, 1),
+    (256, 243),
+    (9, 8),
+    (32, 27),
+    (81, 64),
+    (4, 3),
+    (729, 512),
+    (3, 2),
+    (128, 81),
+    (27, 16),
+    (16, 9),
+    (243, 128),
+];
+
+const WERCKMEISTER_III_CENTS: [f32; 12] = [
+    0.0, 90.225, 192.18, 294.135, 390.225, 498.045, 588.27, 696.09, 792.18, 888.27, 996.09, 1092.18,
+];
+
+impl Temperament {
+    pub fn name(&self) -> &str {
+        match self {
+            Temperament::Equal => "12-tone equal temperament",
+            Temperament::Just => "Just intonation (5-limit)",
+            Temperament::Pythagorean => "Pythagorean",
+            Temperament::QuarterCommaMeantone => "Quarter-comma meantone",
+            Temperament::WerckmeisterIII => "Werckmeister III",
+            Temperament::Custom(_) => "Custom cents offsets",
+        }
+    }
+
+    pub fn cents_above_tonic(&self, degree: Note) -> f32 {
+        let degree = degree as usize % 12;
+        match self {
+            Temperament::Equal => degree as f32 * 100.0,
+            Temperament::Just => ratio_cents(JUST_RATIOS[degree]),
+            Temperament::Pythagorean => ratio_cents(PYTHAGOREAN_RATIOS[degree]),
+            Temperament::QuarterCommaMeantone => {
+                // Degrees are stacked from Eb (3 fifths down) to G# (8 fifths up) in fifths of 696.578 cents
+                let fifth = 1200.0 * (5f32.powf(0.25)).log2();
+                let fifths = (degree as i32 * 7 + 3).rem_euclid(12) - 3;
+                (fifths as f32 * fifth).rem_euclid(1200.0)
+            }
+            Temperament::WerckmeisterIII => WERCKMEISTER_III_CENTS[degree],
+            Temperament::Custom(offsets) => degree as f32 * 100.0 + offsets[degree],
+        }
+    }
+
+    pub fn cents_offset(&self, degree: Note) -> f32 {
+        self.cents_above_tonic(degree) - (degree % 12) as f32 * 100.0
+    }
+}
+
+fn ratio_cents((numerator, denominator): (u32, u32)) -> f32 {
+    1200.0 * (numerator as f32 / denominator as f32).log2()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Intonation {
+    pub temperament: Temperament,
+    pub tonic: Note,
+    pub a4_frequency: f32,
+}
+
+impl Default for Intonation {
+    fn default() -> Self {
+        Self {
+            temperament: Temperament::Equal,
+            tonic: C,
+            a4_frequency: 440.0,
+        }
+    }
+}
+
+impl Intonation {
+    // Tunes the scale from the tonic, shifted so that A4 stays at `a4_frequency` whatever the tonic
+    pub fn frequency(&self, pitch: Pitch) -> f32 {
+        let degree = notes_sub(pitch.note(), self.tonic);
+        let a_offset = self.temperament.cents_offset(notes_sub(A, self.tonic));
+        let cents = self.temperament.cents_offset(degree) - a_offset;
+        pitch.frequency(self.a4_frequency) * 2f32.powf(cents / 1200.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_cents(temperament: &Temperament, degree: Note, cents: f32) {
+        let actual = temperament.cents_above_tonic(degree);
+        assert!(
+            (actual - cents).abs() < 0.01,
+            "{}: {actual} != {cents}",
+            temperament.name()
+        );
+    }
+
+    #[test]
+    fn temperaments_tune_known_intervals() {
+        // Major third and perfect fifth
+        assert_cents(&Temperament::Just, 4, 386.31);
+        assert_cents(&Temperament::Just, 7, 701.96);
+        assert_cents(&Temperament::Pythagorean, 4, 407.82);
+        assert_cents(&Temperament::Pythagorean, 7, 701.96);
+        assert_cents(&Temperament::QuarterCommaMeantone, 4, 386.31);
+        assert_cents(&Temperament::QuarterCommaMeantone, 7, 696.58);
+        assert_cents(&Temperament::WerckmeisterIII, 4, 390.23);
+        assert_cents(&Temperament::WerckmeisterIII, 7, 696.09);
+        for temperament in &TEMPERAMENTS {
+            assert_cents(temperament, 0, 0.0);
+            assert_cents(temperament, 12, 0.0);
+        }
+
+        let mut offsets = [0.0; 12];
+        offsets[4] = -13.69;
+        let custom = Temperament::Custom(offsets);
+        assert_cents(&custom, 4, 386.31);
+        assert!((custom.cents_offset(4) + 13.69).abs() < 0.01);
+        assert_cents(&custom, 7, 700.0);
+    }
+
+    #[test]
+    fn intonation_retunes_relative_to_the_tonic() {
+        let equal = Intonation {
+            a4_frequency: 432.0,
+            ..Intonation::default()
+        };
+        for midi in 0..=127 {
+            assert_eq!(equal.frequency(Pitch(midi)), Pitch(midi).frequency(432.0));
+        }
+
+        let just_in_a = Intonation {
+            temperament: Temperament::Just,
+            tonic: A,
+            a4_frequency: 440.0,
+        };
+        assert_eq!(just_in_a.frequency(A4), 440.0);
+        assert!((just_in_a.frequency(Pitch::new(E, 5)) - 660.0).abs() < 0.01);
+        assert!((just_in_a.frequency(Pitch::new(CS, 5)) - 550.0).abs() < 0.01);
+
+        let just_in_c = Intonation {
+            temperament: Temperament::Just,
+            tonic: C,
+            a4_frequency: 440.0,
+        };
+        assert!((just_in_c.frequency(A4) - 440.0).abs() < 0.01);
+        // A is the major sixth 5/3 above C
+        assert!((just_in_c.frequency(Pitch::new(C, 5)) - 528.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::guitar::{BASS_SCALE_LENGTH, DEFAULT_FRET_COUNT, GUITAR_SCALE_LENGTH, Guitar};
+use crate::notes::Pitch;
+use std::sync::LazyLock;
+
+const BARITONE_SCALE_LENGTH: f32 = 686.0;
+const UKULELE_SCALE_LENGTH: f32 = 380.0;
+const MANDOLIN_SCALE_LENGTH: f32 = 350.0;
+const BANJO_SCALE_LENGTH: f32 = 660.0;
+
+pub struct TuningPreset {
+    name: String,
+    category: String,
+    // Lowest string first, the way tunings are usually written
+    pitches: Vec<Pitch>,
+    fret_count: u8,
+    treble_scale_length: f32,
+    bass_scale_length: f32,
+}
+
+impl TuningPreset {
+    pub fn new(name: &str, category: &str, pitches: &str, fret_count: u8, scale_length: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            category: category.to_string(),
+            pitches: pitches.split_whitespace().map(|p| p.parse().unwrap()).collect(),
+            fret_count,
+            treble_scale_length: scale_length,
+            bass_scale_length: scale_length,
+        }
+    }
+
+    pub fn fanned(mut self, bass_scale_length: f32) -> Self {
+        self.bass_scale_length = bass_scale_length;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+
+    pub fn pitches(&self) -> &[Pitch] {
+        &self.pitches
+    }
+
+    pub fn guitar(&self) -> Guitar {
+        let tunings = self.pitches.iter().rev().copied().collect::<Vec<_>>();
+        let mut guitar = Guitar::new(&tunings, self.fret_count, self.treble_scale_length);
+        if self.bass_scale_length != self.treble_scale_length {
+            guitar.set_scale_lengths(self.treble_scale_length, self.bass_scale_length);
+            guitar.perpendicular_fret = 7;
+        }
+        guitar
+    }
+
+    // Only the open string pitches count, a capo or a different fret count is still the same tuning
+    pub fn matches(&self, guitar: &Guitar) -> bool {
+        guitar.guitar_strings.len() == self.pitches.len()
+            && guitar
+                .guitar_strings
+                .iter()
+                .rev()
+                .zip(&self.pitches)
+                .all(|(s, &p)| s.tuning == p)
+    }
+
+    pub fn description(&self) -> String {
+        self.pitches.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+pub fn all_tunings() -> &'static [TuningPreset] {
+    static ALL_TUNINGS: LazyLock<Vec<TuningPreset>> = LazyLock::new(|| {
+        let guitar =
+            |name, pitches| TuningPreset::new(name, "Guitar", pitches, DEFAULT_FRET_COUNT, GUITAR_SCALE_LENGTH);
+        let drop = |name, pitches| TuningPreset::new(name, "Drop", pitches, DEFAULT_FRET_COUNT, GUITAR_SCALE_LENGTH);
+        let open = |name, pitches| TuningPreset::new(name, "Open", pitches, DEFAULT_FRET_COUNT, GUITAR_SCALE_LENGTH);
+        let extended = |name, pitches| TuningPreset::new(name, "Extended range", pitches, 24, GUITAR_SCALE_LENGTH);
+        let bass = |name, pitches| TuningPreset::new(name, "Bass", pitches, 20, BASS_SCALE_LENGTH);
+        vec![
+            // Guitar
+            guitar("Standard", "E2 A2 D3 G3 B3 E4"),
+            guitar("Half-step down", "Eb2 Ab2 Db3 Gb3 Bb3 Eb4"),
+            guitar("Whole-step down", "D2 G2 C3 F3 A3 D4"),
+            guitar("All fourths", "E2 A2 D3 G3 C4 F4"),
+            guitar("New standard", "C2 G2 D3 A3 E4 G4"),
+            guitar("Nashville high-strung", "E3 A3 D4 G4 B3 E4"),
+            guitar("DADGAD", "D2 A2 D3 G3 A3 D4"),
+            // Drop
+            drop("Drop D", "D2 A2 D3 G3 B3 E4"),
+            drop("Double drop D", "D2 A2 D3 G3 B3 D4"),
+            drop("Drop C#", "Db2 Ab2 Db3 Gb3 Bb3 Eb4"),
+            drop("Drop C", "C2 G2 C3 F3 A3 D4"),
+            drop("Drop B", "B1 F#2 B2 E3 G#3 C#4"),
+            // Open
+            open("Open D", "D2 A2 D3 F#3 A3 D4"),
+            open("Open D minor", "D2 A2 D3 F3 A3 D4"),
+            open("Open G", "D2 G2 D3 G3 B3 D4"),
+            open("Open E", "E2 B2 E3 G#3 B3 E4"),
+            open("Open A", "E2 A2 E3 A3 C#4 E4"),
+            open("Open C", "C2 G2 C3 G3 C4 E4"),
+            // Extended range
+            extended("7-string standard", "B1 E2 A2 D3 G3 B3 E4"),
+            extended("7-string drop A", "A1 E2 A2 D3 G3 B3 E4"),
+            extended("8-string standard", "F#1 B1 E2 A2 D3 G3 B3 E4").fanned(BARITONE_SCALE_LENGTH),
+            extended("8-string drop E", "E1 B1 E2 A2 D3 G3 B3 E4").fanned(BARITONE_SCALE_LENGTH),
+            TuningPreset::new(
+                "Baritone (B standard)",
+                "Extended range",
+                "B1 E2 A2 D3 F#3 B3",
+                24,
+                BARITONE_SCALE_LENGTH,
+            ),
+            // Bass
+            bass("4-string standard", "E1 A1 D2 G2"),
+            bass("4-string drop D", "D1 A1 D2 G2"),
+            bass("5-string standard", "B0 E1 A1 D2 G2"),
+            bass("5-string high C", "E1 A1 D2 G2 C3"),
+            bass("6-string bass", "B0 E1 A1 D2 G2 C3").fanned(889.0),
+            TuningPreset::new("Bass VI", "Bass", "E1 A1 D2 G2 B2 E3", 21, 762.0),
+            // Ukulele, re-entrant tunings are listed in string order rather than by pitch
+            TuningPreset::new("Ukulele (GCEA)", "Ukulele", "G4 C4 E4 A4", 18, UKULELE_SCALE_LENGTH),
+            TuningPreset::new("Ukulele low G", "Ukulele", "G3 C4 E4 A4", 18, UKULELE_SCALE_LENGTH),
+            TuningPreset::new("Ukulele (ADF#B)", "Ukulele", "A4 D4 F#4 B4", 18, UKULELE_SCALE_LENGTH),
+            TuningPreset::new("Baritone ukulele", "Ukulele", "D3 G3 B3 E4", 19, 485.0),
+            // Mandolin family, one string per course
+            TuningPreset::new(
+                "Mandolin",
+                "Mandolin and banjo",
+                "G3 D4 A4 E5",
+                20,
+                MANDOLIN_SCALE_LENGTH,
+            ),
+            TuningPreset::new("Octave mandolin", "Mandolin and banjo", "G2 D3 A3 E4", 20, 530.0),
+            // The short 5th string of a 5-string banjo is treated as a full-length string
+            TuningPreset::new(
+                "Banjo open G",
+                "Mandolin and banjo",
+                "G4 D3 G3 B3 D4",
+                22,
+                BANJO_SCALE_LENGTH,
+            ),
+            TuningPreset::new(
+                "Banjo double C",
+                "Mandolin and banjo",
+                "G4 C3 G3 C4 D4",
+                22,
+                BANJO_SCALE_LENGTH,
+            ),
+            TuningPreset::new("Tenor banjo", "Mandolin and banjo", "C3 G3 D4 A4", 19, 585.0),
+        ]
+    });
+    &ALL_TUNINGS
+}
+
+pub fn find_tuning(guitar: &Guitar) -> Option<&'static TuningPreset> {
+    all_tunings().iter().find(|preset| preset.matches(guitar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_unique_and_rebuild_the_builtin_instruments() {
+        for (i, a) in all_tunings().iter().enumerate() {
+            for b in &all_tunings()[i + 1..] {
+                assert!(a.pitches() != b.pitches(), "{} and {}", a.name(), b.name());
+            }
+            assert!(a.matches(&a.guitar()), "{}", a.name());
+        }
+        assert_eq!(
+            find_tuning(&Guitar::guitar_6_string_standard()).unwrap().guitar(),
+            Guitar::guitar_6_string_standard()
+        );
+        assert_eq!(
+            find_tuning(&Guitar::bass_5_string_standard()).unwrap().guitar(),
+            Guitar::bass_5_string_standard()
+        );
+    }
+
+    #[test]
+    fn detects_hand_edited_tunings() {
+        let mut guitar = Guitar::guitar_6_string_standard();
+        guitar.set_capo(3);
+        guitar.guitar_strings[5].tuning = guitar.guitar_strings[5].tuning - 2;
+        assert_eq!(find_tuning(&guitar).unwrap().name(), "Drop D");
+        guitar.guitar_strings[0].tuning = guitar.guitar_strings[0].tuning - 2;
+        assert_eq!(find_tuning(&guitar).unwrap().name(), "Double drop D");
+        guitar.guitar_strings[0].tuning = guitar.guitar_strings[0].tuning - 1;
+        assert!(find_tuning(&guitar).is_none());
+    }
+}
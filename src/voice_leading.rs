@@ -0,0 +1,269 @@
+use crate::chords::Chord;
+use crate::fret_pattern::{FretPattern, StringState};
+use crate::guitar::{DEFAULT_FRET_COUNT, Guitar, MAX_FRET_SPAN};
+use crate::notes::{Note, Pitch};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VoiceLeadingOptions {
+    pub max_fret_span: u8,
+    pub lowest_fret: u8,
+    pub highest_fret: u8,
+    pub position_shift_weight: f32,
+    pub finger_movement_weight: f32,
+    pub common_tone_weight: f32,
+}
+
+impl Default for VoiceLeadingOptions {
+    fn default() -> Self {
+        Self {
+            max_fret_span: MAX_FRET_SPAN,
+            lowest_fret: 0,
+            highest_fret: DEFAULT_FRET_COUNT,
+            position_shift_weight: 1.0,
+            finger_movement_weight: 0.5,
+            common_tone_weight: 2.0,
+        }
+    }
+}
+
+impl VoiceLeadingOptions {
+    fn allows(&self, pattern: &FretPattern) -> bool {
+        let (low, high) = fretted(pattern).fold((u8::MAX, 0), |(low, high), f| (low.min(f), high.max(f)));
+        if low == u8::MAX {
+            return true;
+        }
+        high - low < self.max_fret_span && low >= self.lowest_fret && high <= self.highest_fret
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransitionCost {
+    pub position_shift: u8,
+    pub finger_movement: u32,
+    pub displaced_common_tones: usize,
+    pub total: f32,
+}
+
+impl TransitionCost {
+    fn between(guitar: &Guitar, from: &FretPattern, to: &FretPattern, options: &VoiceLeadingOptions) -> Self {
+        let position_shift = hand_position(from).abs_diff(hand_position(to));
+        let finger_movement = from
+            .strings()
+            .iter()
+            .zip(to.strings())
+            .filter_map(|(a, b)| match (a, b) {
+                (StringState::Fretted(a), StringState::Fretted(b)) => Some(a.abs_diff(*b) as u32),
+                _ => None,
+            })
+            .sum::<u32>();
+
+        // A tone shared by both chords should stay at the same pitch instead of jumping to another string or octave
+        let from_pitches = pitches(guitar, from);
+        let to_pitches = pitches(guitar, to);
+        let to_notes = to_pitches.iter().map(|p| p.note()).collect::<Vec<Note>>();
+        let displaced_common_tones = from_pitches
+            .iter()
+            .filter(|p| to_notes.contains(&p.note()) && !to_pitches.contains(p))
+            .count();
+
+        let total = options.position_shift_weight * position_shift as f32
+            + options.finger_movement_weight * finger_movement as f32
+            + options.common_tone_weight * displaced_common_tones as f32;
+        Self {
+            position_shift,
+            finger_movement,
+            displaced_common_tones,
+            total,
+        }
+    }
+}
+
+impl fmt::Display for TransitionCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "hand shifts {} frets, fingers move {} frets, {} common tones displaced (cost {:.1})",
+            self.position_shift, self.finger_movement, self.displaced_common_tones, self.total
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoiceLeading {
+    pub voicings: Vec<FretPattern>,
+    // One entry per pair of consecutive chords
+    pub transitions: Vec<TransitionCost>,
+}
+
+impl VoiceLeading {
+    pub fn total_cost(&self) -> f32 {
+        self.transitions.iter().map(|t| t.total).sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VoiceLeadingError {
+    NoVoicing { chord: usize },
+}
+
+impl fmt::Display for VoiceLeadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoiceLeadingError::NoVoicing { chord } => {
+                write!(f, "chord {} has no voicing within the fret constraints", chord + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoiceLeadingError {}
+
+pub fn optimize_voice_leading(
+    guitar: &Guitar,
+    progression: &[(Note, &Chord)],
+    options: &VoiceLeadingOptions,
+) -> Result<VoiceLeading, VoiceLeadingError> {
+    let candidates = progression
+        .iter()
+        .enumerate()
+        .map(|(i, (root, chord))| {
+            let mut voicings = guitar.chord_voicings(*root, chord);
+            voicings.retain(|v| options.allows(v));
+            match voicings.is_empty() {
+                true => Err(VoiceLeadingError::NoVoicing { chord: i }),
+                false => Ok(voicings),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Viterbi over the candidate voicings: the cheapest path ending in each voicing of the current chord
+    let mut costs = candidates.first().map(|c| vec![0.0; c.len()]).unwrap_or_default();
+    let mut previous: Vec<Vec<usize>> = vec![];
+    for pair in candidates.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        let (best_costs, best_previous): (Vec<f32>, Vec<usize>) = to
+            .iter()
+            .map(|next| {
+                from.iter()
+                    .enumerate()
+                    .map(|(i, voicing)| {
+                        (
+                            costs[i] + TransitionCost::between(guitar, voicing, next, options).total,
+                            i,
+                        )
+                    })
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .unwrap()
+            })
+            .unzip();
+        costs = best_costs;
+        previous.push(best_previous);
+    }
+
+    let Some(mut best) = (0..costs.len()).min_by(|&a, &b| costs[a].total_cmp(&costs[b])) else {
+        return Ok(VoiceLeading {
+            voicings: vec![],
+            transitions: vec![],
+        });
+    };
+    let mut path = vec![best];
+    for back in previous.iter().rev() {
+        best = back[best];
+        path.push(best);
+    }
+    path.reverse();
+
+    let voicings = path
+        .iter()
+        .zip(&candidates)
+        .map(|(&i, voicings)| voicings[i].clone())
+        .collect::<Vec<_>>();
+    let transitions = voicings
+        .windows(2)
+        .map(|pair| TransitionCost::between(guitar, &pair[0], &pair[1], options))
+        .collect();
+    Ok(VoiceLeading { voicings, transitions })
+}
+
+fn fretted(pattern: &FretPattern) -> impl Iterator<Item = u8> + '_ {
+    pattern.strings().iter().filter_map(|state| match *state {
+        StringState::Fretted(fret) => Some(fret),
+        _ => None,
+    })
+}
+
+// Lowest fretted position, open strings don't need the hand
+fn hand_position(pattern: &FretPattern) -> u8 {
+    fretted(pattern).min().unwrap_or(0)
+}
+
+fn pitches(guitar: &Guitar, pattern: &FretPattern) -> Vec<Pitch> {
+    pattern
+        .strings()
+        .iter()
+        .enumerate()
+        .filter_map(|(string, state)| state.fret().map(|f| guitar.fret_to_pitch(string, f)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chord_library::ChordLibrary;
+    use crate::notes::*;
+
+    #[test]
+    fn picks_the_cheapest_path() {
+        let guitar = Guitar::guitar_6_string_standard();
+        let library = ChordLibrary::builtin();
+        let minor7 = library.find(&[0, 3, 7, 10]).unwrap();
+        let dominant7 = library.find(&[0, 4, 7, 10]).unwrap();
+        let major7 = library.find(&[0, 4, 7, 11]).unwrap();
+        let progression = [(C, minor7), (F, dominant7), (AS, major7)];
+
+        let options = VoiceLeadingOptions::default();
+        let voice_leading = optimize_voice_leading(&guitar, &progression, &options).unwrap();
+        assert_eq!(voice_leading.voicings.len(), 3);
+        assert_eq!(voice_leading.transitions.len(), 2);
+        for ((root, chord), voicing) in progression.iter().zip(&voice_leading.voicings) {
+            assert!(guitar.chord_voicings(*root, chord).contains(voicing));
+        }
+
+        // Any other choice of voicings costs at least as much
+        let first = guitar.chord_voicings(C, minor7);
+        let second = guitar.chord_voicings(F, dominant7);
+        let fixed_last = &voice_leading.voicings[2];
+        for a in &first {
+            for b in &second {
+                let cost = TransitionCost::between(&guitar, a, b, &options).total
+                    + TransitionCost::between(&guitar, b, fixed_last, &options).total;
+                assert!(cost >= voice_leading.total_cost() - 1e-3, "{a}");
+            }
+        }
+    }
+
+    #[test]
+    fn reports_chords_without_voicings() {
+        let guitar = Guitar::guitar_6_string_standard();
+        let library = ChordLibrary::builtin();
+        let major = library.find(&[0, 4, 7]).unwrap();
+        let options = VoiceLeadingOptions {
+            lowest_fret: 12,
+            highest_fret: 12,
+            ..Default::default()
+        };
+        let error = optimize_voice_leading(&guitar, &[(C, major), (G, major)], &options).unwrap_err();
+        assert_eq!(error, VoiceLeadingError::NoVoicing { chord: 0 });
+    }
+
+    #[test]
+    fn finger_movement_does_not_overflow() {
+        let guitar = Guitar::new(&[Pitch::new(E, 4); 12], 24, 648.0);
+        let low = FretPattern::from_frets(&[Some(1); 12]);
+        let high = FretPattern::from_frets(&[Some(24); 12]);
+        let cost = TransitionCost::between(&guitar, &low, &high, &VoiceLeadingOptions::default());
+        assert_eq!(cost.finger_movement, 12 * 23);
+    }
+}